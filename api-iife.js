@@ -13,6 +13,20 @@ class CORSFetch {
       connectTimeout: undefined,
       maxRedirections: undefined,
       userAgent: navigator.userAgent,
+      cookieJar: undefined,
+      retry: undefined,
+      maxResponseBytes: undefined,
+      maxCompressionRatio: undefined,
+      resolve: undefined,
+      http1Only: undefined,
+      http2PriorKnowledge: undefined,
+      http2KeepAlive: undefined,
+      http3: undefined,
+      unixSocket: undefined,
+      // coalesce body chunks into fewer IPC round trips, e.g. `{ minBytes: 64 * 1024, maxWait: 10 }`
+      readBatch: false,
+      // push body chunks through a channel as they arrive, `true` or `{ credits }`
+      streamBody: false,
       danger: {
         acceptInvalidCerts: false,
         acceptInvalidHostnames: false,
@@ -36,6 +50,7 @@ class CORSFetch {
 
     let rid = null;
     let responseRid = null;
+    let bodyStream = null;
     let isFinished = false;
 
     const cleanup = (_reason) => {
@@ -55,7 +70,10 @@ class CORSFetch {
       }
     };
 
-    const onAbort = () => cleanup("abort");
+    const onAbort = () => {
+      bodyStream?.error(this._cancel_error);
+      cleanup("abort");
+    };
     signal?.addEventListener("abort", onAbort);
 
     const {
@@ -64,6 +82,18 @@ class CORSFetch {
       proxy = this._config.request.proxy,
       danger = this._config.request.danger,
       userAgent = this._config.request.userAgent,
+      cookieJar = this._config.request.cookieJar,
+      retry = this._config.request.retry,
+      maxResponseBytes = this._config.request.maxResponseBytes,
+      maxCompressionRatio = this._config.request.maxCompressionRatio,
+      readBatch = this._config.request.readBatch,
+      streamBody = this._config.request.streamBody,
+      resolve = this._config.request.resolve,
+      http1Only = this._config.request.http1Only,
+      http2PriorKnowledge = this._config.request.http2PriorKnowledge,
+      http2KeepAlive = this._config.request.http2KeepAlive,
+      http3 = this._config.request.http3,
+      unixSocket = this._config.request.unixSocket,
       ...nativeInit
     } = init || {};
 
@@ -84,24 +114,54 @@ class CORSFetch {
           proxy,
           danger,
           userAgent,
+          // only forward an explicit mode, `Request` defaults to "same-origin"
+          credentials: init?.credentials,
+          cookieJar,
+          retry,
+          priority: init?.priority,
+          maxResponseBytes,
+          maxCompressionRatio,
+          integrity: req.integrity || undefined,
+          resolve,
+          http1Only,
+          http2PriorKnowledge,
+          http2KeepAlive,
+          http3,
+          unixSocket,
         },
       });
 
       if (signal?.aborted) throw this._cancel_error;
 
+      if (streamBody) {
+        bodyStream = this._bodyStream(streamBody, () => responseRid, cleanup);
+      }
+
       const {
         status,
         statusText,
         url,
         headers: responseHeaders,
+        httpVersion,
         rid: _rid,
       } = await this.invoke("plugin:cors-fetch|fetch_send", {
         rid,
+        onBody: bodyStream?.channel,
+        credits: bodyStream?.credits,
       });
       responseRid = _rid;
+      bodyStream?.ack();
 
       if (signal?.aborted) throw this._cancel_error;
 
+      // server-sent events are forwarded as soon as they arrive
+      const isEventStream = responseHeaders.some(
+        ([name, value]) =>
+          name.toLowerCase() === "content-type" &&
+          value.toLowerCase().startsWith("text/event-stream"),
+      );
+      const readOptions = readBatch && !isEventStream ? readBatch : undefined;
+
       const readChunk = async (controller) => {
         if (signal?.aborted) {
           controller.error(this._cancel_error);
@@ -111,10 +171,11 @@ class CORSFetch {
         try {
           const data = await this.invoke("plugin:cors-fetch|fetch_read_body", {
             rid: responseRid,
+            options: readOptions,
           });
           const dataUint8 = new Uint8Array(data);
           const lastByte = dataUint8[dataUint8.byteLength - 1];
-          const actualData = dataUint8.slice(0, dataUint8.byteLength - 1);
+          const actualData = dataUint8.subarray(0, dataUint8.byteLength - 1);
 
           // close when the signal to close (last byte is 1) is sent from the IPC.
           if (lastByte === 1) {
@@ -131,9 +192,14 @@ class CORSFetch {
 
       // no body for 101, 103, 204, 205 and 304
       // see https://fetch.spec.whatwg.org/#null-body-status
-      const body = [101, 103, 204, 205, 304].includes(status)
+      const isNullBody = [101, 103, 204, 205, 304].includes(status);
+      // nothing will read the body, so free it and its concurrency slot now
+      if (isNullBody) cleanup();
+      const body = isNullBody
         ? null
-        : new ReadableStream({ pull: readChunk, cancel: onAbort });
+        : bodyStream
+          ? bodyStream.body
+          : new ReadableStream({ pull: readChunk, cancel: onAbort });
 
       const res = new Response(body, {
         status,
@@ -146,6 +212,8 @@ class CORSFetch {
       // Since url and headers are read only properties
       // this is the only way to set them.
       Object.defineProperty(res, "url", { value: url });
+      // not part of the standard `Response`, e.g. "HTTP/2.0"
+      Object.defineProperty(res, "httpVersion", { value: httpVersion });
       Object.defineProperty(res, "headers", {
         value: new Headers(responseHeaders),
       });
@@ -157,6 +225,114 @@ class CORSFetch {
     }
   }
 
+  /**
+   * Export a cookie jar as Netscape `cookies.txt` or JSON.
+   * Requires the `cors-fetch:allow-export-cookies` permission.
+   */
+  exportCookies({ format = "netscape", cookieJar, domains } = {}) {
+    return this.invoke("plugin:cors-fetch|export_cookies", {
+      format,
+      cookieJar,
+      domains,
+    });
+  }
+
+  /**
+   * Import cookies from Netscape `cookies.txt` or JSON, merging by default.
+   * Requires the `cors-fetch:allow-import-cookies` permission.
+   */
+  importCookies(data, { format = "netscape", mode, cookieJar, domains } = {}) {
+    return this.invoke("plugin:cors-fetch|import_cookies", {
+      format,
+      data,
+      mode,
+      cookieJar,
+      domains,
+    });
+  }
+
+  /**
+   * Creates a response body fed by a Tauri channel, which Rust pushes chunks to
+   * as they arrive. Chunks are acknowledged as they are consumed, so Rust never
+   * runs more than `credits` chunks ahead of the reader.
+   */
+  _bodyStream(options, getRid, onError) {
+    const internals = window.__TAURI_INTERNALS__;
+    // Rust clamps the credits the same way
+    const credits = Math.min(Math.max(Math.floor(options.credits ?? 16), 1), 1024);
+    let controller;
+    let isDone = false;
+    let unacked = 0;
+
+    const ack = () => {
+      const rid = getRid();
+      if (rid === null || unacked === 0) return;
+      this.invoke("plugin:cors-fetch|fetch_ack_body", {
+        rid,
+        credits: unacked,
+      }).catch(() => {});
+      unacked = 0;
+    };
+
+    const error = (e) => {
+      if (isDone) return;
+      isDone = true;
+      controller.error(e);
+    };
+
+    const onMessage = (data) => {
+      if (isDone) return;
+      const dataUint8 = new Uint8Array(data);
+      const lastByte = dataUint8[dataUint8.byteLength - 1];
+      const actualData = dataUint8.subarray(0, dataUint8.byteLength - 1);
+
+      // same framing as `fetch_read_body`, plus 2 for an error message
+      if (lastByte === 0) {
+        controller.enqueue(actualData);
+        unacked += 1;
+      } else if (lastByte === 1) {
+        isDone = true;
+        controller.close();
+      } else {
+        error(new TextDecoder().decode(actualData));
+        onError();
+      }
+    };
+
+    // messages carry their index, since large ones may arrive out of order
+    const pending = new Map();
+    let nextIndex = 0;
+    const id = internals.transformCallback((message) => {
+      pending.set(message.index, message);
+      while (pending.has(nextIndex)) {
+        const { end, message: data } = pending.get(nextIndex);
+        pending.delete(nextIndex);
+        nextIndex += 1;
+        if (end) {
+          internals.unregisterCallback?.(id);
+        } else {
+          onMessage(data);
+        }
+      }
+    });
+
+    const body = new ReadableStream(
+      {
+        start: (c) => {
+          controller = c;
+        },
+        pull: ack,
+        cancel: () => {
+          isDone = true;
+          onError();
+        },
+      },
+      new CountQueuingStrategy({ highWaterMark: credits }),
+    );
+
+    return { channel: `__CHANNEL__:${id}`, credits, body, ack, error };
+  }
+
   _cancel_error = "User cancelled the request";
 
   get invoke() {
@@ -192,8 +368,8 @@ class CORSFetch {
       return this._matchesPattern(url, include);
     }
 
-    // Default: proxy all http(s) requests
-    return /^https?:\/\//i.test(url);
+    // Default: proxy all http(s) requests, and Unix socket ones the webview can't send
+    return /^(https?|http\+unix):\/\//i.test(url);
   }
 
   _deepMerge(target, source) {
@@ -4,6 +4,9 @@ const COMMANDS: &[&str] = &[
     "fetch_send",
     "fetch_read_body",
     "fetch_cancel_body",
+    "fetch_ack_body",
+    "export_cookies",
+    "import_cookies",
 ];
 
 fn main() {
@@ -0,0 +1,230 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use crate::{Error, Result};
+
+/// The compression ratio is only checked past this many decoded bytes, since
+/// the first compressed bytes of a stream can legitimately expand a lot.
+const RATIO_CHECK_MIN_BYTES: u64 = 1024 * 1024;
+
+/// Limits on a response body, checked as it is read.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct BodyLimits {
+    /// The maximum number of decoded bytes.
+    pub(crate) max_bytes: Option<u64>,
+    /// The maximum ratio of decoded to received bytes.
+    pub(crate) max_compression_ratio: Option<f64>,
+}
+
+impl BodyLimits {
+    /// Combines two sets of limits, keeping the stricter value of each.
+    pub(crate) fn min(self, other: Self) -> Self {
+        fn stricter<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(if b < a { b } else { a }),
+                (a, b) => a.or(b),
+            }
+        }
+
+        Self {
+            max_bytes: stricter(self.max_bytes, other.max_bytes),
+            max_compression_ratio: stricter(
+                self.max_compression_ratio,
+                other.max_compression_ratio,
+            ),
+        }
+    }
+
+    /// Whether the body has to be decoded by us to know how much was received.
+    pub(crate) fn checks_ratio(&self) -> bool {
+        cfg!(feature = "__decompression") && self.max_compression_ratio.is_some()
+    }
+}
+
+/// Counts how much of a response body was read, enforcing its [`BodyLimits`].
+#[derive(Debug, Default)]
+pub(crate) struct BodyMeter {
+    limits: BodyLimits,
+    decoded: u64,
+    /// The bytes received before decoding, when we decode the body ourselves.
+    received: Option<Arc<AtomicU64>>,
+}
+
+impl BodyMeter {
+    pub(crate) fn new(limits: BodyLimits) -> Self {
+        Self {
+            limits,
+            ..Default::default()
+        }
+    }
+
+    /// Accounts for a decoded chunk of `len` bytes.
+    pub(crate) fn check(&mut self, len: usize) -> Result<()> {
+        self.decoded += len as u64;
+
+        if let Some(max) = self.limits.max_bytes {
+            if self.decoded > max {
+                return Err(Error::ResponseTooLarge(max));
+            }
+        }
+
+        if let (Some(max), Some(received)) = (self.limits.max_compression_ratio, &self.received) {
+            let received = received.load(Ordering::Relaxed).max(1);
+            if self.decoded > RATIO_CHECK_MIN_BYTES && self.decoded as f64 / received as f64 > max {
+                return Err(Error::CompressionRatioExceeded(max));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Replaces the body of `res` with `f(body)`.
+pub(crate) fn map_body<B>(
+    res: reqwest::Response,
+    f: impl FnOnce(reqwest::Body) -> B,
+) -> reqwest::Response
+where
+    B: http_body::Body + Send + Sync + 'static,
+    B::Data: Into<bytes::Bytes>,
+    B::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    let url = res.url().clone();
+    let (mut parts, body) = http::Response::from(res).into_parts();
+    keep_url(&mut parts, url);
+    http::Response::from_parts(parts, reqwest::Body::wrap(f(body))).into()
+}
+
+/// Puts back the final URL, which reqwest stores as an extension that is lost
+/// when converting a response to an `http` one.
+fn keep_url(parts: &mut http::response::Parts, url: url::Url) {
+    use reqwest::ResponseBuilderExt;
+
+    let (with_url, ()) = http::Response::builder()
+        .url(url)
+        .body(())
+        .expect("empty response")
+        .into_parts();
+    parts.extensions.extend(with_url.extensions);
+}
+
+#[cfg(feature = "__decompression")]
+pub(crate) use decompression::{accept_encoding, decompress};
+
+/// Decodes response bodies ourselves instead of letting reqwest do it, so the
+/// bytes received on the wire can be counted.
+#[cfg(feature = "__decompression")]
+mod decompression {
+    use std::{
+        pin::Pin,
+        sync::{
+            atomic::{AtomicU64, Ordering},
+            Arc,
+        },
+        task::{Context, Poll},
+    };
+
+    use http_body::{Body, Frame, SizeHint};
+    use tower::ServiceExt;
+    use tower_http::decompression::Decompression;
+
+    use super::{keep_url, BodyMeter};
+
+    /// The `Accept-Encoding` reqwest would send for the enabled features.
+    pub(crate) fn accept_encoding() -> &'static str {
+        const ENCODINGS: &[(bool, &str)] = &[
+            (cfg!(feature = "gzip"), "gzip"),
+            (cfg!(feature = "deflate"), "deflate"),
+            (cfg!(feature = "brotli"), "br"),
+            (cfg!(feature = "zstd"), "zstd"),
+        ];
+        static ACCEPT_ENCODING: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+        ACCEPT_ENCODING.get_or_init(|| {
+            ENCODINGS
+                .iter()
+                .filter(|(enabled, _)| *enabled)
+                .map(|(_, encoding)| *encoding)
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+    }
+
+    /// Decodes the body of `res`, counting the received bytes into `meter`.
+    ///
+    /// The request must have been sent by a client without automatic decompression.
+    pub(crate) async fn decompress(
+        res: reqwest::Response,
+        mut meter: BodyMeter,
+    ) -> (reqwest::Response, BodyMeter) {
+        let received = Arc::new(AtomicU64::new(0));
+        meter.received = Some(received.clone());
+
+        let url = res.url().clone();
+        let (parts, body) = http::Response::from(res).into_parts();
+        let mut res = Some(http::Response::from_parts(
+            parts,
+            CountingBody {
+                inner: body,
+                received,
+            },
+        ));
+
+        let decoded = Decompression::new(tower::service_fn(move |_: http::Request<()>| {
+            std::future::ready(Ok::<_, std::convert::Infallible>(
+                res.take().expect("the response is decoded once"),
+            ))
+        }))
+        .oneshot(http::Request::new(()))
+        .await;
+        let (mut parts, body) = match decoded {
+            Ok(res) => res.into_parts(),
+            Err(never) => match never {},
+        };
+
+        keep_url(&mut parts, url);
+
+        (
+            http::Response::from_parts(parts, reqwest::Body::wrap(body)).into(),
+            meter,
+        )
+    }
+
+    /// Counts the bytes of a body as they are received.
+    struct CountingBody {
+        inner: reqwest::Body,
+        received: Arc<AtomicU64>,
+    }
+
+    impl Body for CountingBody {
+        type Data = <reqwest::Body as Body>::Data;
+        type Error = reqwest::Error;
+
+        fn poll_frame(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+            let poll = Pin::new(&mut self.inner).poll_frame(cx);
+            if let Poll::Ready(Some(Ok(frame))) = &poll {
+                if let Some(data) = frame.data_ref() {
+                    self.received
+                        .fetch_add(data.len() as u64, Ordering::Relaxed);
+                }
+            }
+            poll
+        }
+
+        fn is_end_stream(&self) -> bool {
+            self.inner.is_end_stream()
+        }
+
+        fn size_hint(&self) -> SizeHint {
+            self.inner.size_hint()
+        }
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use cookie_store::RawCookie;
+use http::{header, HeaderMap};
+use tauri::{Runtime, Webview};
+
+use crate::{cookies::CookieStoreMutex, HostPattern};
+
+/// Which way cookies are mirrored between the plugin jar and the webview.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CookieSyncDirection {
+    /// Copy `Set-Cookie` results of proxied requests into the webview.
+    ToWebview,
+    /// Import the webview cookies for a URL before each proxied request.
+    FromWebview,
+    Both,
+}
+
+/// Mirrors cookies between the plugin cookie jar and the native webview cookie store,
+/// so sessions started through `fetch` also apply to `<img>` tags and iframes, and
+/// the other way around.
+#[derive(Debug, Clone)]
+pub struct CookieBridge {
+    direction: CookieSyncDirection,
+    domains: Vec<HostPattern>,
+}
+
+impl CookieBridge {
+    pub fn new(direction: CookieSyncDirection) -> Self {
+        Self {
+            direction,
+            domains: Vec::new(),
+        }
+    }
+
+    /// Only syncs cookies for matching hosts. Without any domain, all hosts are synced.
+    pub fn domain(mut self, pattern: impl Into<HostPattern>) -> Self {
+        self.domains.push(pattern.into());
+        self
+    }
+
+    fn applies_to(&self, url: &url::Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        self.domains.is_empty() || self.domains.iter().any(|pattern| pattern.matches(host))
+    }
+
+    fn exports(&self, url: &url::Url) -> bool {
+        self.direction != CookieSyncDirection::FromWebview && self.applies_to(url)
+    }
+
+    /// Copies the webview cookies for `url` into `jar`.
+    pub(crate) fn import<R: Runtime>(
+        &self,
+        webview: &Webview<R>,
+        jar: &CookieStoreMutex,
+        url: &url::Url,
+    ) {
+        if self.direction == CookieSyncDirection::ToWebview || !self.applies_to(url) {
+            return;
+        }
+
+        match webview.cookies_for_url(url.clone()) {
+            Ok(cookies) => jar.store_cookies(cookies, url),
+            Err(_e) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("failed to read webview cookies for {url}: {_e}");
+            }
+        }
+    }
+
+    /// Copies the `Set-Cookie` headers of a response for `url` into the webview.
+    pub(crate) fn export<R: Runtime>(
+        &self,
+        webview: &Webview<R>,
+        headers: &HeaderMap,
+        url: &url::Url,
+    ) {
+        if !self.exports(url) {
+            return;
+        }
+
+        let cookies = headers
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .filter_map(|value| RawCookie::parse(value.to_string()).ok());
+
+        for mut cookie in cookies {
+            // only mirror what the jar itself would store, so a response can't
+            // plant cookies for another domain
+            let Ok(stored) = cookie_store::Cookie::try_from_raw_cookie(&cookie, url) else {
+                continue;
+            };
+            // without a Domain attribute the cookie stays host-only
+            if cookie.path().is_none() {
+                cookie.set_path(String::from(&stored.path));
+            }
+
+            let expired = stored.is_expired();
+            let result = if expired {
+                webview.delete_cookie(cookie)
+            } else {
+                webview.set_cookie(cookie)
+            };
+            if let Err(_e) = result {
+                #[cfg(feature = "tracing")]
+                tracing::error!("failed to sync cookie into the webview: {_e}");
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::sync::Arc;
+
+use http::{HeaderMap, Method};
+
+use crate::Result;
+
+/// The [`crate::Builder::client_builder`] callback.
+pub(crate) type ClientBuilderHook =
+    Arc<dyn Fn(reqwest::ClientBuilder) -> reqwest::ClientBuilder + Send + Sync>;
+
+/// The client of a single request, before it is built.
+pub(crate) struct RequestClient {
+    builder: reqwest::ClientBuilder,
+    /// The app's client, used when the request has no client settings of its own.
+    prebuilt: Option<reqwest::Client>,
+    hook: Option<ClientBuilderHook>,
+    #[cfg(feature = "cookies")]
+    jar: Option<Arc<crate::cookies::CookieStoreMutex>>,
+}
+
+impl RequestClient {
+    pub(crate) fn new(
+        builder: reqwest::ClientBuilder,
+        prebuilt: Option<reqwest::Client>,
+        hook: Option<ClientBuilderHook>,
+    ) -> Self {
+        Self {
+            builder,
+            prebuilt,
+            hook,
+            #[cfg(feature = "cookies")]
+            jar: None,
+        }
+    }
+
+    #[cfg(feature = "cookies")]
+    pub(crate) fn cookie_jar(&mut self, jar: Arc<crate::cookies::CookieStoreMutex>) {
+        self.jar = Some(jar);
+    }
+
+    pub(crate) fn build(self) -> Result<Client> {
+        if let Some(client) = self.prebuilt {
+            return Ok(Client {
+                inner: client,
+                // the prebuilt client doesn't know about the jar, so we handle the cookies
+                #[cfg(feature = "cookies")]
+                jar: self.jar,
+            });
+        }
+
+        #[allow(unused_mut)]
+        let mut builder = self.builder;
+        #[cfg(feature = "cookies")]
+        if let Some(jar) = self.jar {
+            builder = builder.cookie_provider(jar);
+        }
+        if let Some(hook) = &self.hook {
+            builder = hook(builder);
+        }
+        Ok(Client {
+            inner: builder.build()?,
+            #[cfg(feature = "cookies")]
+            jar: None,
+        })
+    }
+}
+
+/// The built client of a request.
+pub(crate) struct Client {
+    inner: reqwest::Client,
+    /// The cookie jar, when it isn't already set on the client.
+    #[cfg(feature = "cookies")]
+    jar: Option<Arc<crate::cookies::CookieStoreMutex>>,
+}
+
+impl Client {
+    pub(crate) fn request(&self, method: Method, url: url::Url) -> reqwest::RequestBuilder {
+        self.inner.request(method, url)
+    }
+
+    /// Adds the cookies of the jar to `headers`, unless the client does it.
+    pub(crate) fn add_cookies(&self, url: &url::Url, headers: &mut HeaderMap) {
+        #[cfg(feature = "cookies")]
+        if let Some(jar) = &self.jar {
+            use reqwest::cookie::CookieStore;
+
+            if !headers.contains_key(http::header::COOKIE) {
+                if let Some(cookies) = jar.cookies(url) {
+                    headers.insert(http::header::COOKIE, cookies);
+                }
+            }
+        }
+        #[cfg(not(feature = "cookies"))]
+        let _ = (url, headers);
+    }
+
+    /// Stores the cookies set by `res` in the jar, unless the client does it.
+    pub(crate) fn store_cookies(&self, res: &reqwest::Response) {
+        #[cfg(feature = "cookies")]
+        if let Some(jar) = &self.jar {
+            use reqwest::cookie::CookieStore;
+
+            jar.set_cookies(
+                &mut res.headers().get_all(http::header::SET_COOKIE).iter(),
+                res.url(),
+            );
+        }
+        #[cfg(not(feature = "cookies"))]
+        let _ = res;
+    }
+}
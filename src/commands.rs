@@ -2,21 +2,250 @@
 // SPDX-License-Identifier: Apache-2.0
 // SPDX-License-Identifier: MIT
 
+use bytes::Bytes;
 use http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode};
 use reqwest::{redirect::Policy, NoProxy};
 use serde::{Deserialize, Serialize};
-use std::{future::Future, pin::Pin, str::FromStr, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap, future::Future, path::PathBuf, pin::Pin, str::FromStr, sync::Arc,
+    time::Duration,
+};
 use tauri::{
-    async_runtime::Mutex, command, Manager, ResourceId, ResourceTable, Runtime, State, Webview,
+    async_runtime::Mutex,
+    command,
+    ipc::{Channel, InvokeResponseBody, JavaScriptChannelId},
+    Manager, ResourceId, ResourceTable, Runtime, State, Webview,
 };
 use tokio::sync::oneshot::{channel, Receiver, Sender};
 
-use crate::{Error, Http, Result};
+use crate::{
+    body::{BodyLimits, BodyMeter},
+    integrity::Integrity,
+    limiter::Priority,
+    Error, Http, Result,
+};
 
-struct ReqwestResponse(reqwest::Response);
+struct ReqwestResponse {
+    res: reqwest::Response,
+    meter: BodyMeter,
+    integrity: Option<Integrity>,
+    /// The part of a chunk that didn't fit in the previous read.
+    pending: Option<Bytes>,
+    ended: bool,
+    failed: bool,
+}
 impl tauri::Resource for ReqwestResponse {}
 
-type CancelableResponseResult = Result<reqwest::Response>;
+impl ReqwestResponse {
+    fn new(res: reqwest::Response, meter: BodyMeter, integrity: Option<Integrity>) -> Self {
+        Self {
+            res,
+            meter,
+            integrity,
+            pending: None,
+            ended: false,
+            failed: false,
+        }
+    }
+
+    /// Reads the next chunk of the body, checking it against the body limits and integrity.
+    ///
+    /// Once the body failed to be read, every later read fails too.
+    async fn next_chunk(&mut self) -> Result<Option<Bytes>> {
+        if let Some(chunk) = self.pending.take() {
+            return Ok(Some(chunk));
+        }
+        if self.failed {
+            return Err(Error::BodyClosed);
+        }
+        if self.ended {
+            return Ok(None);
+        }
+
+        let chunk = self.read_chunk().await;
+        self.failed = chunk.is_err();
+        chunk
+    }
+
+    async fn read_chunk(&mut self) -> Result<Option<Bytes>> {
+        let Some(chunk) = self.res.chunk().await? else {
+            self.ended = true;
+            if let Some(integrity) = self.integrity.take() {
+                integrity.verify()?;
+            }
+            return Ok(None);
+        };
+        self.meter.check(chunk.len())?;
+        if let Some(integrity) = &mut self.integrity {
+            integrity.update(&chunk);
+        }
+        Ok(Some(chunk))
+    }
+
+    /// Reads a batch of chunks as described by `options`, or `None` at the end of the body.
+    ///
+    /// A single chunk is handed over as is, which only copies it if its buffer is
+    /// shared. Chunks are only copied when several are merged into one batch.
+    async fn read(&mut self, options: &ReadOptions) -> Result<Option<Vec<u8>>> {
+        let max = options.max_bytes.unwrap_or(usize::MAX).max(1);
+        let min = options.min_bytes.unwrap_or(0).min(max);
+
+        let Some(first) = self.next_chunk().await? else {
+            return Ok(None);
+        };
+        let first = self.fit(first, max);
+        if first.len() >= min {
+            return Ok(Some(Vec::from(first)));
+        }
+
+        // room for the marker byte `fetch_read_body` appends
+        let mut buf = Vec::with_capacity(min + 1);
+        buf.extend_from_slice(&first);
+
+        let max_wait = options
+            .max_wait
+            .map_or(DEFAULT_READ_MAX_WAIT, Duration::from_millis);
+        let deadline = tokio::time::sleep(max_wait);
+        tokio::pin!(deadline);
+
+        while buf.len() < min {
+            let chunk = tokio::select! {
+                chunk = self.next_chunk() => chunk?,
+                _ = &mut deadline => break,
+            };
+            let Some(chunk) = chunk else {
+                break;
+            };
+            let chunk = self.fit(chunk, max - buf.len());
+            buf.extend_from_slice(&chunk);
+        }
+
+        Ok(Some(buf))
+    }
+
+    /// Returns the part of `chunk` that fits in `room`, keeping the rest for the next read.
+    fn fit(&mut self, mut chunk: Bytes, room: usize) -> Bytes {
+        if chunk.len() > room {
+            self.pending = Some(chunk.split_off(room));
+        }
+        chunk
+    }
+}
+
+/// A response in the resources table, whose body is read by `fetch_read_body`.
+///
+/// Reads of the same rid are serialized, so concurrent readers each get the next
+/// part of the body and never the same chunk twice.
+struct ResponseBody(Mutex<ReqwestResponse>);
+impl tauri::Resource for ResponseBody {}
+
+impl ResponseBody {
+    fn new(response: ReqwestResponse) -> Self {
+        Self(Mutex::new(response))
+    }
+
+    async fn read(&self, options: &ReadOptions) -> Result<Option<Vec<u8>>> {
+        self.0.lock().await.read(options).await
+    }
+}
+
+/// How `fetch_read_body` coalesces body chunks into a single reply.
+///
+/// Without options every reply carries a single chunk, as soon as it arrives.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadOptions {
+    /// Keep reading until at least this many bytes are buffered.
+    min_bytes: Option<usize>,
+    /// Never reply with more than this many bytes, keeping the rest for the next read.
+    max_bytes: Option<usize>,
+    /// How long to wait for `min_bytes` after the first chunk, in milliseconds.
+    max_wait: Option<u64>,
+}
+
+const DEFAULT_READ_MAX_WAIT: Duration = Duration::from_millis(10);
+
+/// How many chunks are pushed to a body channel before the first ack.
+const DEFAULT_STREAM_CREDITS: u32 = 16;
+/// The most chunks a body channel may run ahead of the webview, whatever it asks for.
+const MAX_STREAM_CREDITS: u32 = 1024;
+
+/// A response body pushed to the webview through a [`Channel`] as it arrives.
+///
+/// Each chunk consumes a credit, which the webview hands back with `fetch_ack_body`
+/// once it has processed the chunk, so a slow consumer pauses the stream instead of
+/// letting it buffer. Closing the resource stops the stream.
+struct BodyStream {
+    credits: Arc<tokio::sync::Semaphore>,
+    /// The credits the stream started with, which acks never go over.
+    window: usize,
+    _cancel: Sender<()>,
+}
+impl tauri::Resource for BodyStream {}
+
+impl BodyStream {
+    /// Starts pushing the body of `response` to `on_body`, returning the resource id of the stream.
+    fn spawn<R: Runtime>(
+        webview: Webview<R>,
+        response: ReqwestResponse,
+        on_body: Channel,
+        credits: u32,
+    ) -> ResourceId {
+        let (cancel_tx, cancel_rx) = channel();
+        // without a credit the stream would never start
+        let window = credits.clamp(1, MAX_STREAM_CREDITS) as usize;
+        let credits = Arc::new(tokio::sync::Semaphore::new(window));
+        let rid = webview.resources_table().add(BodyStream {
+            credits: credits.clone(),
+            window,
+            _cancel: cancel_tx,
+        });
+
+        tauri::async_runtime::spawn(async move {
+            tokio::select! {
+                _ = Self::push(response, &on_body, &credits) => {
+                    let _ = webview.resources_table().close(rid);
+                }
+                _ = cancel_rx => {}
+            }
+        });
+
+        rid
+    }
+
+    /// Sends every chunk with a trailing 0 byte, then `[1]` at the end of the body,
+    /// or the error message with a trailing 2 byte.
+    async fn push(
+        mut response: ReqwestResponse,
+        channel: &Channel,
+        credits: &tokio::sync::Semaphore,
+    ) -> tauri::Result<()> {
+        loop {
+            // the semaphore is never closed
+            if let Ok(credit) = credits.acquire().await {
+                credit.forget();
+            }
+
+            let data = match response.next_chunk().await {
+                Ok(Some(chunk)) => {
+                    let mut data = Vec::with_capacity(chunk.len() + 1);
+                    data.extend_from_slice(&chunk);
+                    data.push(0);
+                    data
+                }
+                Ok(None) => return channel.send(InvokeResponseBody::Raw(vec![1])),
+                Err(e) => {
+                    let mut data = e.to_string().into_bytes();
+                    data.push(2);
+                    return channel.send(InvokeResponseBody::Raw(data));
+                }
+            };
+            channel.send(InvokeResponseBody::Raw(data))?;
+        }
+    }
+}
+
+type CancelableResponseResult = Result<ReqwestResponse>;
 type CancelableResponseFuture =
     Pin<Box<dyn Future<Output = CancelableResponseResult> + Send + Sync>>;
 
@@ -63,6 +292,8 @@ pub struct FetchResponse {
     status_text: String,
     headers: Vec<(String, String)>,
     url: String,
+    /// The negotiated protocol, e.g. `HTTP/1.1` or `HTTP/2.0`.
+    http_version: String,
     rid: ResourceId,
 }
 
@@ -82,9 +313,60 @@ pub struct ClientConfig {
     data: Option<Vec<u8>>,
     connect_timeout: Option<u64>,
     max_redirections: Option<usize>,
-    proxy: Option<Proxy>,
+    proxy: Option<ProxyOption>,
     danger: Option<DangerousSettings>,
     user_agent: Option<String>,
+    credentials: Option<Credentials>,
+    cookie_jar: Option<String>,
+    retry: Option<crate::retry::RetryConfig>,
+    priority: Option<Priority>,
+    max_response_bytes: Option<u64>,
+    max_compression_ratio: Option<f64>,
+    integrity: Option<String>,
+    resolve: Option<HashMap<String, Vec<String>>>,
+    http1_only: Option<bool>,
+    http2_prior_knowledge: Option<bool>,
+    http2_keep_alive: Option<Http2KeepAlive>,
+    http3: Option<bool>,
+    unix_socket: Option<PathBuf>,
+}
+
+/// HTTP/2 PING frames keeping a connection alive.
+#[cfg(feature = "http2")]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Http2KeepAlive {
+    /// In milliseconds.
+    interval: u64,
+    /// How long to wait for the PING to be acknowledged before closing the connection, in milliseconds.
+    timeout: Option<u64>,
+    /// Also send PINGs while no request is in flight.
+    while_idle: Option<bool>,
+}
+
+/// Without the `http2` feature the option is only deserialized to be rejected.
+#[cfg(not(feature = "http2"))]
+type Http2KeepAlive = serde::de::IgnoredAny;
+
+/// The fetch `credentials` mode, deciding whether the cookie jar is used.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Credentials {
+    /// Neither send nor store cookies.
+    Omit,
+    /// Use the cookie jar only for origins configured on the [`crate::Builder`].
+    SameOrigin,
+    #[default]
+    Include,
+}
+
+/// The fetch `proxy` option.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ProxyOption {
+    /// The name of a profile registered with [`crate::Builder::proxy_profile`].
+    Profile(String),
+    Proxy(Box<Proxy>),
 }
 
 #[derive(Debug, Deserialize)]
@@ -107,32 +389,85 @@ pub enum UrlOrConfig {
 #[serde(rename_all = "camelCase")]
 pub struct ProxyConfig {
     url: String,
+    /// Replaces the scheme of `url`, which may then be just `host:port`.
+    kind: Option<ProxyKind>,
     basic_auth: Option<BasicAuth>,
+    /// Sent as basic auth to HTTP proxies, or in the SOCKS5 handshake.
+    username: Option<String>,
+    password: Option<String>,
     no_proxy: Option<String>,
 }
 
+/// The protocol spoken to a proxy.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyKind {
+    Http,
+    Https,
+    /// SOCKS5, resolving hostnames locally.
+    Socks5,
+    /// SOCKS5, letting the proxy resolve hostnames.
+    Socks5h,
+}
+
+impl ProxyKind {
+    fn scheme(self) -> &'static str {
+        match self {
+            Self::Http => "http",
+            Self::Https => "https",
+            Self::Socks5 => "socks5",
+            Self::Socks5h => "socks5h",
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct BasicAuth {
     username: String,
     password: String,
 }
 
+/// Applies the proxy `kind` to `url`, and checks that its scheme is supported.
+fn proxy_url(url: String, kind: Option<ProxyKind>) -> crate::Result<String> {
+    let url = match kind {
+        Some(kind) => {
+            let address = url.split_once("://").map_or(url.as_str(), |(_, rest)| rest);
+            format!("{}://{address}", kind.scheme())
+        }
+        None => url,
+    };
+
+    let is_socks = url
+        .split_once("://")
+        .is_some_and(|(scheme, _)| scheme.to_ascii_lowercase().starts_with("socks"));
+    if is_socks && !cfg!(feature = "socks") {
+        return Err(Error::SocksNotEnabled);
+    }
+    Ok(url)
+}
+
 #[inline]
 fn proxy_creator(
     url_or_config: UrlOrConfig,
     proxy_fn: fn(String) -> reqwest::Result<reqwest::Proxy>,
-) -> reqwest::Result<reqwest::Proxy> {
+) -> crate::Result<reqwest::Proxy> {
     match url_or_config {
-        UrlOrConfig::Url(url) => Ok(proxy_fn(url)?),
+        UrlOrConfig::Url(url) => Ok(proxy_fn(proxy_url(url, None)?)?),
         UrlOrConfig::Config(ProxyConfig {
             url,
+            kind,
             basic_auth,
+            username,
+            password,
             no_proxy,
         }) => {
-            let mut proxy = proxy_fn(url)?;
+            let mut proxy = proxy_fn(proxy_url(url, kind)?)?;
             if let Some(basic_auth) = basic_auth {
                 proxy = proxy.basic_auth(&basic_auth.username, &basic_auth.password);
             }
+            if let Some(username) = username {
+                proxy = proxy.basic_auth(&username, password.as_deref().unwrap_or_default());
+            }
             if let Some(no_proxy) = no_proxy {
                 proxy = proxy.no_proxy(NoProxy::from_string(&no_proxy));
             }
@@ -165,6 +500,44 @@ fn attach_proxy(
     Ok(builder)
 }
 
+/// Applies the HTTP version options, HTTP/2 ones needing the `http2` feature.
+fn http_versions(
+    mut builder: reqwest::ClientBuilder,
+    http1_only: bool,
+    http2_prior_knowledge: bool,
+    keep_alive: Option<Http2KeepAlive>,
+) -> crate::Result<reqwest::ClientBuilder> {
+    if http1_only {
+        builder = builder.http1_only();
+    }
+
+    #[cfg(feature = "http2")]
+    {
+        if http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        if let Some(keep_alive) = keep_alive {
+            builder = builder
+                .http2_keep_alive_interval(Duration::from_millis(keep_alive.interval))
+                .http2_keep_alive_while_idle(keep_alive.while_idle.unwrap_or_default());
+            if let Some(timeout) = keep_alive.timeout {
+                builder = builder.http2_keep_alive_timeout(Duration::from_millis(timeout));
+            }
+        }
+    }
+    #[cfg(not(feature = "http2"))]
+    {
+        if http2_prior_knowledge {
+            return Err(Error::FeatureNotEnabled("http2PriorKnowledge", "http2"));
+        }
+        if keep_alive.is_some() {
+            return Err(Error::FeatureNotEnabled("http2KeepAlive", "http2"));
+        }
+    }
+
+    Ok(builder)
+}
+
 #[command]
 pub async fn fetch<R: Runtime>(
     webview: Webview<R>,
@@ -181,9 +554,34 @@ pub async fn fetch<R: Runtime>(
         proxy,
         danger,
         user_agent,
+        credentials,
+        cookie_jar,
+        retry,
+        priority,
+        max_response_bytes,
+        max_compression_ratio,
+        integrity,
+        resolve,
+        http1_only,
+        http2_prior_knowledge,
+        http2_keep_alive,
+        http3,
+        unix_socket,
     } = client_config;
 
+    // a `http+unix` URL names its socket, and is requested as `http://localhost`
+    let (url, unix_socket) = if url.scheme() == crate::unix_socket::SCHEME {
+        let (socket, url) = crate::unix_socket::split_url(&url)?;
+        (url, Some(socket))
+    } else {
+        (url, unix_socket)
+    };
+
+    let http1_only = http1_only.unwrap_or_default();
+    let http2_prior_knowledge = http2_prior_knowledge.unwrap_or_default();
+
     let scheme = url.scheme();
+    let integrity = integrity.as_deref().and_then(Integrity::parse);
     let method = Method::from_bytes(method.as_bytes())?;
 
     let mut headers = HeaderMap::new();
@@ -194,7 +592,20 @@ pub async fn fetch<R: Runtime>(
 
     match scheme {
         "http" | "https" => {
-            let mut builder = reqwest::ClientBuilder::new();
+            let mut builder = state.new_client_builder();
+
+            // whether the request needs its own client instead of the app's prebuilt one
+            let overrides_client = danger
+                .as_ref()
+                .is_some_and(|d| d.accept_invalid_certs || d.accept_invalid_hostnames)
+                || connect_timeout.is_some()
+                || max_redirections.is_some()
+                || proxy.is_some()
+                || resolve.is_some()
+                || http1_only
+                || http2_prior_knowledge
+                || http2_keep_alive.is_some()
+                || unix_socket.is_some();
 
             if let Some(danger_config) = danger {
                 builder = builder
@@ -214,16 +625,61 @@ pub async fn fetch<R: Runtime>(
                 });
             }
 
-            if let Some(proxy_config) = proxy {
-                builder = attach_proxy(proxy_config, builder)?;
+            // a proxy of its own or a Unix socket bypass the app's proxy configuration
+            let uses_app_proxy = proxy.is_none() && unix_socket.is_none();
+            // both replace the app's proxy configuration
+            match proxy {
+                Some(ProxyOption::Profile(name)) => {
+                    builder = builder.no_proxy().proxy(state.proxy_profile(&name)?);
+                }
+                Some(ProxyOption::Proxy(proxy_config)) => {
+                    if !state.allows_request_proxy() {
+                        return Err(Error::ProxyNotAllowed);
+                    }
+                    builder = attach_proxy(*proxy_config, builder.no_proxy())?;
+                }
+                None => {}
+            }
+
+            if let Some(overrides) = resolve {
+                builder = crate::dns::resolve(builder, overrides)?;
+            }
+
+            if let Some(path) = &unix_socket {
+                builder = state.unix_socket(builder, path)?;
+            }
+
+            builder = http_versions(builder, http1_only, http2_prior_knowledge, http2_keep_alive)?;
+
+            let limits = state.body_limits(BodyLimits {
+                max_bytes: max_response_bytes,
+                max_compression_ratio,
+            });
+            // the compressed size is lost once reqwest decoded the body, so decode it ourselves
+            if limits.checks_ratio() {
+                builder = builder.no_gzip().no_brotli().no_zstd().no_deflate();
             }
 
+            let overrides = overrides_client || limits.checks_ratio();
+            #[allow(unused_mut)]
+            let mut client = state.request_client(builder, overrides);
+
+            #[cfg(feature = "cookies")]
+            let mut cookie_export = None;
             #[cfg(feature = "cookies")]
-            {
-                builder = builder.cookie_provider(state.cookies_jar.clone());
+            if state.allows_credentials(credentials.unwrap_or_default(), &url) {
+                let jar = state.cookie_jar(webview.label(), cookie_jar.as_deref())?;
+                if let Some(bridge) = state.cookie_bridge() {
+                    bridge.import(&webview, &jar, &url);
+                    cookie_export = Some((bridge.clone(), webview.clone()));
+                }
+                client.cookie_jar(jar);
             }
+            #[cfg(not(feature = "cookies"))]
+            let _ = (credentials, cookie_jar);
 
-            let mut request = builder.build()?.request(method.clone(), url);
+            let client = client.build()?;
+            let mut request = client.request(method.clone(), url.clone());
 
             // POST and PUT requests should always have a 0 length content-length,
             // if there is no body. https://fetch.spec.whatwg.org/#http-network-or-cache-fetch
@@ -237,14 +693,28 @@ pub async fn fetch<R: Runtime>(
                 headers.append(header::ACCEPT_ENCODING, HeaderValue::from_str("identity")?);
             }
 
-            // Set User Agent
-            if !headers.contains_key(header::USER_AGENT) && user_agent.is_some() {
+            #[cfg(feature = "__decompression")]
+            if limits.checks_ratio() && !headers.contains_key(header::ACCEPT_ENCODING) {
                 headers.append(
-                    header::USER_AGENT,
-                    HeaderValue::from_str(user_agent.unwrap().as_str())?,
+                    header::ACCEPT_ENCODING,
+                    HeaderValue::from_static(crate::body::accept_encoding()),
                 );
             }
 
+            // Set User Agent
+            if let Some(user_agent) = user_agent {
+                if !headers.contains_key(header::USER_AGENT) {
+                    headers.append(header::USER_AGENT, HeaderValue::from_str(&user_agent)?);
+                }
+            }
+
+            // Origin and Referer are decided in Rust, so the page can't override them
+            if let Some(policy) = state.origin_policy(&url) {
+                policy.apply(&mut headers, &url)?;
+            }
+
+            client.add_cookies(&url, &mut headers);
+
             if let Some(data) = data {
                 request = request.body(data);
             }
@@ -254,7 +724,50 @@ pub async fn fetch<R: Runtime>(
             #[cfg(feature = "tracing")]
             tracing::trace!("{:?}", request);
 
-            let fut = async move { request.send().await.map_err(Into::into) };
+            let dispatch = state.dispatch(
+                &method,
+                &url,
+                state.retry_policy(retry)?,
+                priority.unwrap_or_default(),
+            );
+            #[cfg(feature = "http3")]
+            let dispatch = dispatch.http3(http3.unwrap_or_default());
+            let dispatch = if uses_app_proxy {
+                dispatch
+            } else {
+                dispatch.without_pac()
+            };
+            #[cfg(not(feature = "http3"))]
+            if http3.unwrap_or_default() {
+                return Err(Error::FeatureNotEnabled("http3", "http3"));
+            }
+
+            // queueing and retries live inside the stored future, so cancelling the
+            // request also takes it out of the queue and stops any pending retry
+            let fut = async move {
+                let (res, permit) = dispatch.send(request).await?;
+                client.store_cookies(&res);
+                // the concurrency slot is held until the body is read to the end or cancelled
+                let res = match permit {
+                    Some(permit) => permit.hold(res),
+                    None => res,
+                };
+
+                #[cfg(feature = "cookies")]
+                if let Some((bridge, webview)) = cookie_export {
+                    bridge.export(&webview, res.headers(), res.url());
+                }
+
+                let meter = BodyMeter::new(limits);
+                #[cfg(feature = "__decompression")]
+                let (res, meter) = if limits.checks_ratio() {
+                    crate::body::decompress(res, meter).await
+                } else {
+                    (res, meter)
+                };
+
+                Ok(ReqwestResponse::new(res, meter, integrity))
+            };
 
             let mut resources_table = webview.resources_table();
             let rid = resources_table.add_request(Box::pin(fut));
@@ -276,7 +789,13 @@ pub async fn fetch<R: Runtime>(
             #[cfg(feature = "tracing")]
             tracing::trace!("{:?}", response);
 
-            let fut = async move { Ok(reqwest::Response::from(response)) };
+            let fut = async move {
+                Ok(ReqwestResponse::new(
+                    reqwest::Response::from(response),
+                    BodyMeter::default(),
+                    integrity,
+                ))
+            };
             let mut resources_table = webview.resources_table();
             let rid = resources_table.add_request(Box::pin(fut));
             Ok(rid)
@@ -300,6 +819,8 @@ pub fn fetch_cancel<R: Runtime>(webview: Webview<R>, rid: ResourceId) -> crate::
 pub async fn fetch_send<R: Runtime>(
     webview: Webview<R>,
     rid: ResourceId,
+    on_body: Option<JavaScriptChannelId>,
+    credits: Option<u32>,
 ) -> crate::Result<FetchResponse> {
     let (req, abort_rx) = {
         let mut resources_table = webview.resources_table();
@@ -314,7 +835,7 @@ pub async fn fetch_send<R: Runtime>(
 
     let mut fut = req.fut.lock().await;
 
-    let res = tokio::select! {
+    let response = tokio::select! {
         res = fut.as_mut() => res?,
         _ = abort_rx.0 => {
             let mut resources_table = webview.resources_table();
@@ -323,11 +844,14 @@ pub async fn fetch_send<R: Runtime>(
         }
     };
 
+    let res = &response.res;
+
     #[cfg(feature = "tracing")]
     tracing::trace!("{:?}", res);
 
     let status = res.status();
     let url = res.url().to_string();
+    let http_version = format!("{:?}", res.version());
     let mut headers = Vec::new();
     for (key, val) in res.headers().iter() {
         headers.push((
@@ -336,14 +860,22 @@ pub async fn fetch_send<R: Runtime>(
         ));
     }
 
-    let mut resources_table = webview.resources_table();
-    let rid = resources_table.add(ReqwestResponse(res));
+    let rid = match on_body {
+        Some(on_body) => BodyStream::spawn(
+            webview.clone(),
+            response,
+            on_body.channel_on(webview.clone()),
+            credits.unwrap_or(DEFAULT_STREAM_CREDITS),
+        ),
+        None => webview.resources_table().add(ResponseBody::new(response)),
+    };
 
     Ok(FetchResponse {
         status: status.as_u16(),
         status_text: status.canonical_reason().unwrap_or_default().to_string(),
         headers,
         url,
+        http_version,
         rid,
     })
 }
@@ -352,33 +884,33 @@ pub async fn fetch_send<R: Runtime>(
 pub async fn fetch_read_body<R: Runtime>(
     webview: Webview<R>,
     rid: ResourceId,
+    options: Option<ReadOptions>,
 ) -> crate::Result<tauri::ipc::Response> {
     let res = {
         let resources_table = webview.resources_table();
-        resources_table.get::<ReqwestResponse>(rid)?
+        resources_table.get::<ResponseBody>(rid)?
     };
 
-    // SAFETY: we can access the inner value mutably
-    // because we are the only ones with a reference to it
-    // and we don't want to use `Arc::into_inner` because we want to keep the value in the table
-    // for potential future calls to `fetch_cancel_body`
-    let res_ptr = Arc::as_ptr(&res) as *mut ReqwestResponse;
-    let res = unsafe { &mut *res_ptr };
-    let res = &mut res.0;
-
-    let Some(chunk) = res.chunk().await? else {
-        let mut resources_table = webview.resources_table();
-        resources_table.close(rid)?;
-
-        // return a response with a single byte to indicate that the body is empty
-        return Ok(tauri::ipc::Response::new(vec![1]));
-    };
-
-    let mut chunk = chunk.to_vec();
-    // append a 0 byte to indicate that the body is not empty
-    chunk.push(0);
+    match res.read(&options.unwrap_or_default()).await {
+        Ok(Some(mut chunk)) => {
+            // append a 0 byte to indicate that the body is not empty
+            chunk.push(0);
+            Ok(tauri::ipc::Response::new(chunk))
+        }
+        Ok(None) => {
+            // a concurrent read may have closed it already
+            let mut resources_table = webview.resources_table();
+            let _ = resources_table.close(rid);
 
-    Ok(tauri::ipc::Response::new(chunk))
+            // return a response with a single byte to indicate that the body is empty
+            Ok(tauri::ipc::Response::new(vec![1]))
+        }
+        Err(e) => {
+            let mut resources_table = webview.resources_table();
+            let _ = resources_table.close(rid);
+            Err(e)
+        }
+    }
 }
 
 #[command]
@@ -390,3 +922,171 @@ pub async fn fetch_cancel_body<R: Runtime>(
     resources_table.close(rid)?;
     Ok(())
 }
+
+#[command]
+pub fn fetch_ack_body<R: Runtime>(
+    webview: Webview<R>,
+    rid: ResourceId,
+    credits: u32,
+) -> crate::Result<()> {
+    let resources_table = webview.resources_table();
+    let stream = resources_table.get::<BodyStream>(rid)?;
+    // credits only come back for pushed chunks, so never more than the window
+    let room = stream
+        .window
+        .saturating_sub(stream.credits.available_permits());
+    stream.credits.add_permits((credits as usize).min(room));
+    Ok(())
+}
+
+#[cfg(feature = "cookies")]
+#[command]
+pub async fn export_cookies<R: Runtime>(
+    webview: Webview<R>,
+    state: State<'_, Http>,
+    format: crate::CookieFormat,
+    cookie_jar: Option<String>,
+    domains: Option<Vec<String>>,
+) -> crate::Result<String> {
+    let domains = domains
+        .unwrap_or_default()
+        .into_iter()
+        .map(crate::HostPattern::from)
+        .collect::<Vec<_>>();
+    state
+        .cookie_jar(webview.label(), cookie_jar.as_deref())?
+        .export(format, &domains)
+}
+
+#[cfg(feature = "cookies")]
+#[command]
+pub async fn import_cookies<R: Runtime>(
+    webview: Webview<R>,
+    state: State<'_, Http>,
+    format: crate::CookieFormat,
+    data: String,
+    mode: Option<crate::CookieImportMode>,
+    cookie_jar: Option<String>,
+    domains: Option<Vec<String>>,
+) -> crate::Result<usize> {
+    let domains = domains
+        .unwrap_or_default()
+        .into_iter()
+        .map(crate::HostPattern::from)
+        .collect::<Vec<_>>();
+    state
+        .cookie_jar(webview.label(), cookie_jar.as_deref())?
+        .import(format, &data, mode.unwrap_or_default(), &domains)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, sync::Mutex as StdMutex};
+
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+    use super::*;
+
+    const COUNT: u32 = 2000;
+
+    /// Serves `0..COUNT` as big-endian `u32`s, in chunks that don't line up with them.
+    async fn serve_counter() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = tokio::io::AsyncReadExt::read(&mut stream, &mut request).await;
+
+            let body = (0..COUNT).flat_map(u32::to_be_bytes).collect::<Vec<_>>();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n")
+                .await
+                .unwrap();
+            for chunk in body.chunks(7) {
+                stream
+                    .write_all(format!("{:x}\r\n", chunk.len()).as_bytes())
+                    .await
+                    .unwrap();
+                stream.write_all(chunk).await.unwrap();
+                stream.write_all(b"\r\n").await.unwrap();
+            }
+            stream.write_all(b"0\r\n\r\n").await.unwrap();
+        });
+        format!("http://{addr}/")
+    }
+
+    async fn response_body(url: &str) -> ResponseBody {
+        let res = reqwest::get(url).await.unwrap();
+        ResponseBody::new(ReqwestResponse::new(res, BodyMeter::default(), None))
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn parallel_reads_of_one_rid_are_serialized() {
+        let url = serve_counter().await;
+        let table = Arc::new(StdMutex::new(ResourceTable::default()));
+        let body = response_body(&url).await;
+        let rid = table.lock().unwrap().add(body);
+
+        // every read returns exactly one number
+        let options = Arc::new(ReadOptions {
+            min_bytes: Some(4),
+            max_bytes: Some(4),
+            max_wait: Some(10_000),
+        });
+
+        let readers = (0..16)
+            .map(|_| {
+                let table = table.clone();
+                let options = options.clone();
+                tokio::spawn(async move {
+                    let mut numbers = Vec::new();
+                    loop {
+                        let Ok(res) = table.lock().unwrap().get::<ResponseBody>(rid) else {
+                            break;
+                        };
+                        match res.read(&options).await.unwrap() {
+                            Some(buf) => numbers.push(u32::from_be_bytes(buf.try_into().unwrap())),
+                            None => {
+                                let _ = table.lock().unwrap().close(rid);
+                                break;
+                            }
+                        }
+                    }
+                    numbers
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut seen = HashSet::new();
+        for reader in readers {
+            let numbers = reader.await.unwrap();
+            // each reader sees the body in order
+            assert!(numbers.windows(2).all(|pair| pair[0] < pair[1]));
+            for number in numbers {
+                assert!(seen.insert(number), "{number} was read twice");
+            }
+        }
+        assert_eq!(seen, (0..COUNT).collect());
+    }
+
+    #[tokio::test]
+    async fn reads_after_the_end_return_none() {
+        let url = serve_counter().await;
+        let res = Arc::new(response_body(&url).await);
+
+        let mut total = 0;
+        while let Some(buf) = res.read(&ReadOptions::default()).await.unwrap() {
+            total += buf.len();
+        }
+        assert_eq!(total, COUNT as usize * 4);
+
+        let reads = (0..8).map(|_| {
+            let res = res.clone();
+            tokio::spawn(async move { res.read(&ReadOptions::default()).await })
+        });
+        for read in reads {
+            assert!(read.await.unwrap().unwrap().is_none());
+        }
+    }
+}
@@ -0,0 +1,170 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::{collections::HashMap, sync::Arc};
+
+use cookie_store::CookieStore;
+use serde::Serialize;
+
+/// The Tauri event emitted for every [`CookieChange`] when enabled with
+/// [`crate::Builder::emit_cookie_events`].
+pub const COOKIE_CHANGE_EVENT: &str = "cors-fetch://cookie-change";
+
+/// Why a cookie changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CookieChangeCause {
+    /// Added, or updated with a new value, by a response, an import or the webview.
+    Set,
+    /// Expired by the server, e.g. with `Max-Age=0`.
+    Expired,
+    /// Dropped because its expiry date passed.
+    Evicted,
+    /// Removed explicitly, e.g. by clearing the jar or replacing it on import.
+    Explicit,
+}
+
+/// A cookie that was added, updated or removed in a cookie jar.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CookieChange {
+    /// The name of the jar the cookie belongs to.
+    pub jar: String,
+    pub domain: String,
+    pub path: String,
+    pub name: String,
+    pub cause: CookieChangeCause,
+    /// The new value of `Set` changes, `None` unless values are explicitly included.
+    pub value: Option<String>,
+}
+
+pub(crate) type CookieListener = Arc<dyn Fn(&CookieChange) + Send + Sync>;
+
+type CookieKey = (String, String, String);
+
+/// The values of the unexpired cookies of a store, keyed by domain, path and name.
+pub(crate) struct Snapshot(HashMap<CookieKey, String>);
+
+/// Reports the changes made to a single cookie jar.
+#[derive(Clone)]
+pub(crate) struct CookieEvents {
+    jar: String,
+    listener: CookieListener,
+    include_values: bool,
+}
+
+impl std::fmt::Debug for CookieEvents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CookieEvents")
+            .field("jar", &self.jar)
+            .field("include_values", &self.include_values)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CookieEvents {
+    pub(crate) fn new(listener: CookieListener, include_values: bool) -> Self {
+        Self {
+            jar: String::new(),
+            listener,
+            include_values,
+        }
+    }
+
+    /// Returns the same listener, reporting changes for the jar called `jar`.
+    pub(crate) fn for_jar(&self, jar: &str) -> Self {
+        Self {
+            jar: jar.to_string(),
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn snapshot(store: &CookieStore) -> Snapshot {
+        Snapshot(
+            store
+                .iter_unexpired()
+                .filter_map(|cookie| {
+                    let key = (
+                        cookie.domain.as_cow()?.into_owned(),
+                        String::from(&cookie.path),
+                        cookie.name().to_string(),
+                    );
+                    Some((key, cookie.value().to_string()))
+                })
+                .collect(),
+        )
+    }
+
+    /// Removes the cookies whose expiry date passed, reporting them as evicted.
+    pub(crate) fn evict(&self, store: &mut CookieStore) -> Vec<CookieChange> {
+        let expired = store
+            .iter_any()
+            .filter(|cookie| cookie.is_expired())
+            .filter_map(|cookie| {
+                Some((
+                    cookie.domain.as_cow()?.into_owned(),
+                    String::from(&cookie.path),
+                    cookie.name().to_string(),
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        expired
+            .into_iter()
+            .map(|(domain, path, name)| {
+                store.remove(&domain, &path, &name);
+                self.change((domain, path, name), CookieChangeCause::Evicted, None)
+            })
+            .collect()
+    }
+
+    /// Compares `store` with an earlier snapshot, reporting cookies that are gone with `removed`,
+    /// or as evicted if they are still stored but expired in the meantime.
+    pub(crate) fn diff(
+        &self,
+        before: Snapshot,
+        store: &CookieStore,
+        removed: CookieChangeCause,
+    ) -> Vec<CookieChange> {
+        let mut before = before.0;
+        let mut changes = Vec::new();
+        for (key, value) in Self::snapshot(store).0 {
+            // a cookie set again with the same value only refreshes its expiry
+            if before.remove(&key).as_ref() != Some(&value) {
+                changes.push(self.change(key, CookieChangeCause::Set, Some(value)));
+            }
+        }
+        for key in before.into_keys() {
+            let (domain, path, name) = &key;
+            let cause = match store.get_any(domain, path, name) {
+                Some(_) => CookieChangeCause::Evicted,
+                None => removed,
+            };
+            changes.push(self.change(key, cause, None));
+        }
+        changes
+    }
+
+    pub(crate) fn notify(&self, changes: Vec<CookieChange>) {
+        for change in changes {
+            (self.listener)(&change);
+        }
+    }
+
+    fn change(
+        &self,
+        (domain, path, name): CookieKey,
+        cause: CookieChangeCause,
+        value: Option<String>,
+    ) -> CookieChange {
+        CookieChange {
+            jar: self.jar.clone(),
+            domain,
+            path,
+            name,
+            cause,
+            value: value.filter(|_| self.include_values),
+        }
+    }
+}
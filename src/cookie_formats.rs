@@ -0,0 +1,425 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use cookie::{time, SameSite};
+use cookie_store::{CookieDomain, CookieExpiration, CookieStore, RawCookie};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, HostPattern, Result};
+
+/// A serialization format for importing and exporting cookie jars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CookieFormat {
+    /// The Netscape `cookies.txt` format used by curl, wget and most browser extensions.
+    Netscape,
+    /// A JSON array of [`CookieRecord`].
+    Json,
+}
+
+/// How imported cookies are combined with the ones already in the jar.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CookieImportMode {
+    /// Keep existing cookies, overwriting those with the same domain, path and name.
+    #[default]
+    Merge,
+    /// Remove existing cookies in the imported domains first.
+    Replace,
+}
+
+/// A single cookie in the JSON import and export format.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CookieRecord {
+    pub name: String,
+    pub value: String,
+    /// The cookie domain, without a leading dot.
+    pub domain: String,
+    /// Whether the cookie is only sent to `domain` itself and not to its subdomains.
+    #[serde(default)]
+    pub host_only: bool,
+    #[serde(default = "default_path")]
+    pub path: String,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default)]
+    pub http_only: bool,
+    /// `Strict`, `Lax` or `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub same_site: Option<String>,
+    /// Expiry as a Unix timestamp in seconds, `None` for session cookies.
+    #[serde(default)]
+    pub expires: Option<i64>,
+}
+
+fn default_path() -> String {
+    "/".into()
+}
+
+fn in_domains(domain: &str, domains: &[HostPattern]) -> bool {
+    domains.is_empty() || domains.iter().any(|pattern| pattern.matches(domain))
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Exports the unexpired cookies of `store` whose domain matches `domains`.
+pub(crate) fn export(
+    store: &CookieStore,
+    format: CookieFormat,
+    domains: &[HostPattern],
+) -> Result<String> {
+    let records = store
+        .iter_unexpired()
+        .filter_map(|cookie| {
+            let domain = cookie.domain.as_cow()?.into_owned();
+            in_domains(&domain, domains).then(|| CookieRecord {
+                name: cookie.name().to_string(),
+                value: cookie.value().to_string(),
+                host_only: matches!(cookie.domain, CookieDomain::HostOnly(_)),
+                domain,
+                path: String::from(&cookie.path),
+                secure: cookie.secure().unwrap_or(false),
+                http_only: cookie.http_only().unwrap_or(false),
+                same_site: cookie.same_site().map(|s| s.to_string()),
+                expires: match cookie.expires {
+                    CookieExpiration::AtUtc(at) => Some(at.unix_timestamp()),
+                    CookieExpiration::SessionEnd => None,
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    match format {
+        CookieFormat::Json => Ok(serde_json::to_string_pretty(&records)?),
+        CookieFormat::Netscape => Ok(to_netscape(&records)),
+    }
+}
+
+/// Imports cookies into `store`, returning how many were stored.
+pub(crate) fn import(
+    store: &mut CookieStore,
+    format: CookieFormat,
+    data: &str,
+    mode: CookieImportMode,
+    domains: &[HostPattern],
+) -> Result<usize> {
+    let records = match format {
+        CookieFormat::Json => serde_json::from_str::<Vec<CookieRecord>>(data)?,
+        CookieFormat::Netscape => from_netscape(data)?,
+    };
+
+    // convert every record before touching the jar, so a malformed one leaves it as it was
+    let now = unix_now();
+    let cookies = records
+        .iter()
+        .filter(|record| {
+            in_domains(&record.domain, domains) && !record.expires.is_some_and(|at| at <= now)
+        })
+        .map(|record| to_raw_cookie(record, now))
+        .collect::<Result<Vec<_>>>()?;
+
+    if mode == CookieImportMode::Replace {
+        remove(store, domains);
+    }
+
+    Ok(cookies
+        .iter()
+        .filter(|(cookie, url)| store.insert_raw(cookie, url).is_ok())
+        .count())
+}
+
+/// Removes the cookies of `store` whose domain matches `domains`, returning how many were removed.
+pub(crate) fn remove(store: &mut CookieStore, domains: &[HostPattern]) -> usize {
+    let stale = store
+        .iter_any()
+        .filter_map(|cookie| {
+            let domain = cookie.domain.as_cow()?.into_owned();
+            in_domains(&domain, domains).then(|| {
+                (
+                    domain,
+                    String::from(&cookie.path),
+                    cookie.name().to_string(),
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+    for (domain, path, name) in &stale {
+        store.remove(domain, path, name);
+    }
+    stale.len()
+}
+
+/// Builds a cookie as if it was set by a response from its own domain.
+fn to_raw_cookie(record: &CookieRecord, now: i64) -> Result<(RawCookie<'static>, url::Url)> {
+    let invalid =
+        |reason: &str| Error::CookieImport(format!("invalid cookie {}: {reason}", record.name));
+
+    if record.name.is_empty()
+        || record
+            .name
+            .contains(|c: char| c == '=' || c == ';' || c.is_whitespace() || c.is_control())
+    {
+        return Err(invalid("invalid name"));
+    }
+    if record.value.contains(|c: char| c == ';' || c.is_control()) {
+        return Err(invalid("invalid value"));
+    }
+    if !record.path.starts_with('/') {
+        return Err(invalid("the path must start with `/`"));
+    }
+    let host = url::Host::parse(&record.domain).map_err(|e| invalid(&e.to_string()))?;
+
+    let mut cookie = RawCookie::build((record.name.clone(), record.value.clone()))
+        .path(record.path.clone())
+        .secure(record.secure)
+        .http_only(record.http_only);
+    if !record.host_only {
+        cookie = cookie.domain(record.domain.clone());
+    }
+    if let Some(expires) = record.expires {
+        cookie = cookie.max_age(time::Duration::seconds(expires - now));
+    }
+    if let Some(same_site) = &record.same_site {
+        cookie = cookie.same_site(match same_site.to_ascii_lowercase().as_str() {
+            "strict" => SameSite::Strict,
+            "lax" => SameSite::Lax,
+            "none" => SameSite::None,
+            _ => return Err(invalid("SameSite must be `Strict`, `Lax` or `None`")),
+        });
+    }
+
+    let url = url::Url::parse(&format!("https://{host}{}", record.path))?;
+    Ok((cookie.build(), url))
+}
+
+const NETSCAPE_HEADER: &str = "# Netscape HTTP Cookie File";
+const NETSCAPE_HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+fn to_netscape(records: &[CookieRecord]) -> String {
+    let mut out = format!("{NETSCAPE_HEADER}\n\n");
+    for record in records {
+        let prefix = if record.http_only {
+            NETSCAPE_HTTP_ONLY_PREFIX
+        } else {
+            ""
+        };
+        let (domain, include_subdomains) = if record.host_only {
+            (record.domain.clone(), "FALSE")
+        } else {
+            (format!(".{}", record.domain), "TRUE")
+        };
+        let secure = if record.secure { "TRUE" } else { "FALSE" };
+        out.push_str(&format!(
+            "{prefix}{domain}\t{include_subdomains}\t{}\t{secure}\t{}\t{}\t{}\n",
+            record.path,
+            record.expires.unwrap_or(0),
+            record.name,
+            record.value,
+        ));
+    }
+    out
+}
+
+fn from_netscape(data: &str) -> Result<Vec<CookieRecord>> {
+    let mut records = Vec::new();
+    for (index, line) in data.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        let (line, http_only) = match line.strip_prefix(NETSCAPE_HTTP_ONLY_PREFIX) {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid = || Error::CookieImport(format!("invalid cookies.txt line {}", index + 1));
+        let fields = line.split('\t').collect::<Vec<_>>();
+        let [domain, include_subdomains, path, secure, expires, name, value] = fields[..] else {
+            return Err(invalid());
+        };
+        let expires = expires.parse::<i64>().map_err(|_| invalid())?;
+
+        records.push(CookieRecord {
+            name: name.to_string(),
+            value: value.to_string(),
+            domain: domain.trim_start_matches('.').to_string(),
+            host_only: !include_subdomains.eq_ignore_ascii_case("TRUE"),
+            path: path.to_string(),
+            secure: secure.eq_ignore_ascii_case("TRUE"),
+            http_only,
+            same_site: None,
+            // 0 marks a session cookie
+            expires: (expires != 0).then_some(expires),
+        });
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FUTURE: i64 = 4_102_444_800;
+
+    fn netscape_line(record: &str) -> String {
+        format!("{NETSCAPE_HEADER}\n\n{record}\n")
+    }
+
+    fn json_record(name: &str, domain: &str) -> String {
+        format!(
+            r#"{{ "name": "{name}", "value": "v", "domain": "{domain}", "expires": {FUTURE} }}"#
+        )
+    }
+
+    fn names(store: &CookieStore) -> Vec<String> {
+        let mut names = store
+            .iter_any()
+            .map(|cookie| cookie.name().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn netscape_lines_are_parsed() {
+        let data = format!(
+            "{NETSCAPE_HEADER}\r\n# a comment\r\n\r\n\
+             .example.com\tTRUE\t/\tTRUE\t{FUTURE}\tshared\t1\r\n\
+             #HttpOnly_api.example.com\tFALSE\t/v1\tFALSE\t0\tsession\ta=b\r\n"
+        );
+        let records = from_netscape(&data).unwrap();
+        assert_eq!(records.len(), 2);
+
+        let shared = &records[0];
+        assert_eq!(shared.domain, "example.com");
+        assert!(!shared.host_only && shared.secure && !shared.http_only);
+        assert_eq!(shared.expires, Some(FUTURE));
+
+        let session = &records[1];
+        assert_eq!(
+            (session.domain.as_str(), session.path.as_str()),
+            ("api.example.com", "/v1")
+        );
+        assert_eq!(session.value, "a=b");
+        assert!(session.host_only && session.http_only && !session.secure);
+        assert_eq!(session.expires, None);
+    }
+
+    #[test]
+    fn malformed_netscape_lines_report_their_number() {
+        let data = netscape_line(".example.com\tTRUE\t/\tFALSE\tsoon\tname\tvalue");
+        assert!(
+            matches!(from_netscape(&data), Err(Error::CookieImport(e)) if e.ends_with("line 3"))
+        );
+
+        let data = netscape_line(".example.com\tTRUE\t/\tFALSE\t0\tname");
+        assert!(
+            matches!(from_netscape(&data), Err(Error::CookieImport(e)) if e.ends_with("line 3"))
+        );
+    }
+
+    #[test]
+    fn json_records_are_imported_with_defaults() {
+        let mut store = CookieStore::default();
+        let data = format!(
+            r#"[{}, {{ "name": "strict", "value": "1", "domain": "example.com", "hostOnly": true,
+                "path": "/app", "secure": true, "httpOnly": true, "sameSite": "Strict" }}]"#,
+            json_record("plain", "example.com"),
+        );
+        let imported = import(
+            &mut store,
+            CookieFormat::Json,
+            &data,
+            CookieImportMode::Merge,
+            &[],
+        );
+        assert_eq!(imported.unwrap(), 2);
+
+        let plain = store.get("example.com", "/", "plain").unwrap();
+        assert!(matches!(plain.domain, CookieDomain::Suffix(_)));
+        assert!(plain.is_persistent());
+
+        let strict = store.get("example.com", "/app", "strict").unwrap();
+        assert!(matches!(strict.domain, CookieDomain::HostOnly(_)));
+        assert_eq!(strict.secure(), Some(true));
+        assert_eq!(strict.http_only(), Some(true));
+        assert_eq!(strict.same_site(), Some(SameSite::Strict));
+        assert!(!strict.is_persistent());
+    }
+
+    #[test]
+    fn exported_cookies_import_back() {
+        for format in [CookieFormat::Json, CookieFormat::Netscape] {
+            let mut store = CookieStore::default();
+            let data = format!("[{}]", json_record("kept", "example.com"));
+            import(
+                &mut store,
+                CookieFormat::Json,
+                &data,
+                CookieImportMode::Merge,
+                &[],
+            )
+            .unwrap();
+
+            let exported = export(&store, format, &[]).unwrap();
+            let mut copy = CookieStore::default();
+            let imported = import(&mut copy, format, &exported, CookieImportMode::Merge, &[]);
+            assert_eq!(imported.unwrap(), 1);
+            assert_eq!(names(&copy), ["kept"]);
+        }
+    }
+
+    #[test]
+    fn replace_only_clears_the_jar_once_every_record_is_valid() {
+        let mut store = CookieStore::default();
+        let data = format!(
+            "[{}, {}]",
+            json_record("a", "example.com"),
+            json_record("b", "other.com")
+        );
+        import(
+            &mut store,
+            CookieFormat::Json,
+            &data,
+            CookieImportMode::Merge,
+            &[],
+        )
+        .unwrap();
+
+        let domains = [HostPattern::new("example.com")];
+        let malformed = format!(
+            "[{}, {}]",
+            json_record("c", "example.com"),
+            json_record("bad name", "example.com")
+        );
+        let result = import(
+            &mut store,
+            CookieFormat::Json,
+            &malformed,
+            CookieImportMode::Replace,
+            &domains,
+        );
+        assert!(matches!(result, Err(Error::CookieImport(_))));
+        assert_eq!(names(&store), ["a", "b"]);
+
+        let data = format!("[{}]", json_record("c", "example.com"));
+        import(
+            &mut store,
+            CookieFormat::Json,
+            &data,
+            CookieImportMode::Replace,
+            &domains,
+        )
+        .unwrap();
+        assert_eq!(names(&store), ["b", "c"]);
+    }
+}
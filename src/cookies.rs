@@ -5,12 +5,26 @@
 // taken from https://github.com/pfernie/reqwest_cookie_store/blob/2ec4afabcd55e24d3afe3f0626ee6dc97bed938d/src/lib.rs
 
 use std::{
-    path::PathBuf,
-    sync::{mpsc::Receiver, Mutex},
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{mpsc::Receiver, Arc, Mutex},
+    time::{Duration, Instant},
 };
 
+#[cfg(feature = "cookies-encryption")]
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
 use cookie_store::{CookieStore, RawCookie, RawCookieParseError};
 use reqwest::header::HeaderValue;
+use tokio::io::AsyncWriteExt;
+
+use crate::{
+    cookie_events::{CookieChangeCause, CookieEvents},
+    cookie_formats::{CookieFormat, CookieImportMode},
+    HostPattern,
+};
 
 fn set_cookies(
     cookie_store: &mut CookieStore,
@@ -43,61 +57,213 @@ fn cookies(cookie_store: &CookieStore, url: &url::Url) -> Option<HeaderValue> {
 
 /// A [`cookie_store::CookieStore`] wrapped internally by a [`std::sync::Mutex`], suitable for use in
 /// async/concurrent contexts.
+///
+/// Jars without a `path` are ephemeral and never persisted.
 #[derive(Debug)]
 pub struct CookieStoreMutex {
-    pub path: PathBuf,
-    store: Mutex<CookieStore>,
+    pub path: Option<PathBuf>,
+    store: Arc<Mutex<CookieStore>>,
     save_task: Mutex<Option<CancellableTask>>,
+    write_lock: Arc<tokio::sync::Mutex<()>>,
+    save_debounce: Duration,
+    save_max_wait: Duration,
+    /// When the oldest change that no save picked up yet happened.
+    unsaved_since: Arc<Mutex<Option<Instant>>>,
+    events: Option<CookieEvents>,
+    #[cfg(feature = "cookies-encryption")]
+    cipher: Option<CookieCipher>,
 }
 
 impl CookieStoreMutex {
     /// Create a new [`CookieStoreMutex`] from an existing [`cookie_store::CookieStore`].
-    pub fn new(path: PathBuf, cookie_store: CookieStore) -> CookieStoreMutex {
+    pub fn new(path: Option<PathBuf>, cookie_store: CookieStore) -> CookieStoreMutex {
         CookieStoreMutex {
             path,
-            store: Mutex::new(cookie_store),
+            store: Arc::new(Mutex::new(cookie_store)),
             save_task: Default::default(),
+            write_lock: Default::default(),
+            save_debounce: DEFAULT_SAVE_DEBOUNCE,
+            save_max_wait: DEFAULT_SAVE_MAX_WAIT,
+            unsaved_since: Default::default(),
+            events: None,
+            #[cfg(feature = "cookies-encryption")]
+            cipher: None,
         }
     }
 
-    pub fn load<R: std::io::BufRead>(
-        path: PathBuf,
-        reader: R,
-    ) -> cookie_store::Result<CookieStoreMutex> {
-        cookie_store::serde::load(reader, |c| serde_json::from_str(c))
-            .map(|store| CookieStoreMutex::new(path, store))
+    /// Reports every cookie added, updated or removed in this jar to `events`.
+    pub(crate) fn with_events(mut self, events: Option<CookieEvents>) -> Self {
+        self.events = events;
+        self
     }
 
-    fn cookies_to_str(&self) -> Result<String, serde_json::Error> {
-        let mut cookies = Vec::new();
-        for cookie in self
-            .store
-            .lock()
-            .expect("poisoned cookie jar mutex")
-            .iter_unexpired()
-        {
-            if cookie.is_persistent() {
-                cookies.push(cookie.clone());
-            }
+    /// Encrypts the jar with `cipher` whenever it is persisted.
+    #[cfg(feature = "cookies-encryption")]
+    pub fn with_cipher(mut self, cipher: Option<CookieCipher>) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    /// Coalesces the saves triggered by cookie updates within `debounce`.
+    pub fn with_save_debounce(mut self, debounce: Duration) -> Self {
+        self.save_debounce = debounce;
+        self
+    }
+
+    /// Saves at least once every `max_wait` while cookie updates keep arriving.
+    pub fn with_save_max_wait(mut self, max_wait: Duration) -> Self {
+        self.save_max_wait = max_wait;
+        self
+    }
+
+    /// Stores cookies obtained outside of a response, e.g. from the webview.
+    pub fn store_cookies(&self, cookies: Vec<RawCookie<'static>>, url: &url::Url) {
+        if cookies.is_empty() {
+            return;
+        }
+        self.update(CookieChangeCause::Expired, |store| {
+            store.store_response_cookies(cookies.into_iter(), url)
+        });
+
+        if let Err(_e) = self.schedule_save() {
+            #[cfg(feature = "tracing")]
+            tracing::error!("failed to save cookie jar: {_e}");
         }
-        serde_json::to_string(&cookies)
     }
 
+    /// Exports the cookies whose domain matches `domains`, or all cookies if it is empty.
+    pub fn export(&self, format: CookieFormat, domains: &[HostPattern]) -> crate::Result<String> {
+        crate::cookie_formats::export(&self.store.lock().unwrap(), format, domains)
+    }
+
+    /// Imports cookies whose domain matches `domains`, or all cookies if it is empty.
+    pub fn import(
+        &self,
+        format: CookieFormat,
+        data: &str,
+        mode: CookieImportMode,
+        domains: &[HostPattern],
+    ) -> crate::Result<usize> {
+        let imported = self.update(CookieChangeCause::Explicit, |store| {
+            crate::cookie_formats::import(store, format, data, mode, domains)
+        })?;
+
+        if let Err(_e) = self.schedule_save() {
+            #[cfg(feature = "tracing")]
+            tracing::error!("failed to save cookie jar: {_e}");
+        }
+        Ok(imported)
+    }
+
+    /// Removes the cookies whose domain matches `domains`, or all cookies if it is empty.
+    pub fn clear(&self, domains: &[HostPattern]) -> usize {
+        let removed = self.update(CookieChangeCause::Explicit, |store| {
+            crate::cookie_formats::remove(store, domains)
+        });
+
+        if let Err(_e) = self.schedule_save() {
+            #[cfg(feature = "tracing")]
+            tracing::error!("failed to save cookie jar: {_e}");
+        }
+        removed
+    }
+
+    /// Runs `f` on the store and reports the resulting changes, using `removed`
+    /// as the cause for cookies that disappeared.
+    fn update<T>(&self, removed: CookieChangeCause, f: impl FnOnce(&mut CookieStore) -> T) -> T {
+        let mut store = self.store.lock().unwrap();
+        let Some(events) = &self.events else {
+            return f(&mut store);
+        };
+
+        let mut changes = events.evict(&mut store);
+        let before = CookieEvents::snapshot(&store);
+        let result = f(&mut store);
+        changes.extend(events.diff(before, &store, removed));
+        // drop what `f` expired too, the diff already reported it
+        events.evict(&mut store);
+        // listeners may use the jar, so call them without holding the lock
+        drop(store);
+        events.notify(changes);
+        result
+    }
+
+    /// Persists the jar right away, cancelling any pending debounced save.
     pub fn request_save(&self) -> cookie_store::Result<Receiver<()>> {
-        let cookie_str = self.cookies_to_str()?;
-        let path = self.path.clone();
+        self.spawn_save(None)
+    }
+
+    /// Persists the jar once no other cookie update arrived for the debounce window,
+    /// or once the oldest unsaved update is `save_max_wait` old.
+    fn schedule_save(&self) -> cookie_store::Result<Receiver<()>> {
+        let since = *self
+            .unsaved_since
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Instant::now);
+        let left = self.save_max_wait.saturating_sub(since.elapsed());
+        self.spawn_save(Some(self.save_debounce.min(left)))
+    }
+
+    fn spawn_save(&self, delay: Option<Duration>) -> cookie_store::Result<Receiver<()>> {
         let (tx, rx) = std::sync::mpsc::channel();
+        let Some(path) = self.path.clone() else {
+            // ephemeral jars have nothing to persist
+            let _ = tx.send(());
+            return Ok(rx);
+        };
+
+        let store = self.store.clone();
+        #[cfg(feature = "cookies-encryption")]
+        let cipher = self.cipher.clone();
+        let write_lock = self.write_lock.clone();
+        let unsaved_since = self.unsaved_since.clone();
         let task = tauri::async_runtime::spawn(async move {
-            match tokio::fs::write(&path, &cookie_str).await {
-                Ok(()) => {
-                    let _ = tx.send(());
-                }
-                Err(_e) => {
-                    #[cfg(feature = "tracing")]
-                    tracing::error!("failed to save cookie jar: {_e}");
-                }
+            if let Some(delay) = delay {
+                tokio::time::sleep(delay).await;
             }
+            // the write below serializes the jar after every change so far
+            unsaved_since.lock().unwrap().take();
+
+            // once the debounce window is over the write is detached, so a newer
+            // save can't abort it halfway through
+            let write = tauri::async_runtime::spawn(async move {
+                // writes run one at a time, so an older jar never lands over a newer one
+                let _guard = write_lock.lock().await;
+
+                // serialize only once it's our turn, so the latest cookies are saved
+                let contents = cookies_to_str(&store.lock().expect("poisoned cookie jar mutex"))
+                    .map(String::into_bytes)
+                    .map_err(cookie_store::Error::from);
+                #[cfg(feature = "cookies-encryption")]
+                let contents = contents.and_then(|contents| match &cipher {
+                    Some(cipher) => cipher.encrypt(&contents),
+                    None => Ok(contents),
+                });
+
+                let result = match contents {
+                    Ok(contents) => write_atomically(&path, &contents).await.map_err(Into::into),
+                    Err(e) => Err(e),
+                };
+                #[cfg(feature = "cookies-encryption")]
+                let result = match (result, &cipher) {
+                    (Ok(()), Some(_)) => remove_plaintext_backup(&path).await.map_err(Into::into),
+                    (result, _) => result,
+                };
+                match result {
+                    Ok(()) => {
+                        let _ = tx.send(());
+                    }
+                    Err(_e) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::error!("failed to save cookie jar: {_e}");
+                    }
+                }
+            });
+            let _ = write.await;
         });
+
+        // replacing the previous task aborts it, which is what coalesces the saves
         self.save_task
             .lock()
             .unwrap()
@@ -106,12 +272,95 @@ impl CookieStoreMutex {
     }
 }
 
+/// How long cookie updates are coalesced before the jar is written to disk.
+pub const DEFAULT_SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// The longest a cookie update waits for the jar to be written while updates keep arriving.
+pub const DEFAULT_SAVE_MAX_WAIT: Duration = Duration::from_secs(5);
+
+fn load_store<R: std::io::BufRead>(reader: R) -> cookie_store::Result<CookieStore> {
+    cookie_store::serde::load(reader, |c| serde_json::from_str(c))
+}
+
+fn cookies_to_str(store: &CookieStore) -> Result<String, serde_json::Error> {
+    let mut cookies = Vec::new();
+    for cookie in store.iter_unexpired() {
+        if cookie.is_persistent() {
+            cookies.push(cookie.clone());
+        }
+    }
+    serde_json::to_string(&cookies)
+}
+
+/// Returns `path` with `suffix` appended to its file name.
+///
+/// Jar names escape `~`, so these siblings can't collide with another jar's file.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Writes to a temporary file and renames it over `path`, keeping the previous
+/// file as a backup, so a crash mid-write never leaves a truncated jar behind.
+async fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    // every save gets its own temporary file, so saves never write into each other's
+    let tmp = sibling(
+        path,
+        &format!("{BACKUP_SUFFIX_TMP}{:016x}", fastrand::u64(..)),
+    );
+    if let Err(e) = write_synced(&tmp, contents).await {
+        let _ = tokio::fs::remove_file(&tmp).await;
+        return Err(e);
+    }
+
+    if tokio::fs::try_exists(path).await? {
+        tokio::fs::rename(path, sibling(path, BACKUP_SUFFIX)).await?;
+    }
+    tokio::fs::rename(&tmp, path).await
+}
+
+async fn write_synced(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut file = tokio::fs::File::create(path).await?;
+    file.write_all(contents).await?;
+    file.sync_all().await
+}
+
+/// Deletes the plaintext backup the first encrypted save leaves behind when a
+/// legacy jar is migrated, so its cookies don't stay readable on disk.
+#[cfg(feature = "cookies-encryption")]
+async fn remove_plaintext_backup(path: &Path) -> std::io::Result<()> {
+    let backup = sibling(path, BACKUP_SUFFIX);
+    match tokio::fs::read(&backup).await {
+        Ok(data) if !data.starts_with(ENCRYPTED_MAGIC) => tokio::fs::remove_file(&backup).await,
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// A jar file and its backup, in the order they are loaded.
+fn jar_files(path: &Path) -> [PathBuf; 2] {
+    [path.to_path_buf(), sibling(path, BACKUP_SUFFIX)]
+}
+
+#[cfg(feature = "cookies-encryption")]
+fn file_starts_with(path: &Path, prefix: &[u8]) -> bool {
+    std::fs::read(path)
+        .map(|data| data.starts_with(prefix))
+        .unwrap_or(false)
+}
+
+const BACKUP_SUFFIX: &str = "~bak";
+const BACKUP_SUFFIX_TMP: &str = "~tmp";
+
 impl reqwest::cookie::CookieStore for CookieStoreMutex {
     fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &url::Url) {
-        set_cookies(&mut self.store.lock().unwrap(), cookie_headers, url);
+        self.update(CookieChangeCause::Expired, |store| {
+            set_cookies(store, cookie_headers, url)
+        });
 
-        // try to persist cookies immediately asynchronously
-        if let Err(_e) = self.request_save() {
+        // persist cookies asynchronously once the burst of updates is over
+        if let Err(_e) = self.schedule_save() {
             #[cfg(feature = "tracing")]
             tracing::error!("failed to save cookie jar: {_e}");
         }
@@ -123,6 +372,272 @@ impl reqwest::cookie::CookieStore for CookieStoreMutex {
     }
 }
 
+/// The jar used when no other jar is selected for a request.
+pub const DEFAULT_COOKIE_JAR: &str = "default";
+
+/// A set of named cookie jars, each persisted to its own file and loaded on first use.
+#[derive(Debug)]
+pub struct CookieJars {
+    dir: PathBuf,
+    ephemeral: HashSet<String>,
+    jars: Mutex<HashMap<String, Arc<CookieStoreMutex>>>,
+    save_debounce: Duration,
+    save_max_wait: Duration,
+    events: Option<CookieEvents>,
+    #[cfg(feature = "cookies-encryption")]
+    cipher: Option<CookieCipher>,
+}
+
+impl CookieJars {
+    pub fn new(dir: PathBuf, ephemeral: HashSet<String>) -> Self {
+        Self {
+            dir,
+            ephemeral,
+            jars: Default::default(),
+            save_debounce: DEFAULT_SAVE_DEBOUNCE,
+            save_max_wait: DEFAULT_SAVE_MAX_WAIT,
+            events: None,
+            #[cfg(feature = "cookies-encryption")]
+            cipher: None,
+        }
+    }
+
+    /// Reports the cookie changes of every jar to `events`.
+    pub(crate) fn with_events(mut self, events: Option<CookieEvents>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Coalesces the saves of every jar within `debounce`.
+    pub fn with_save_debounce(mut self, debounce: Duration) -> Self {
+        self.save_debounce = debounce;
+        self
+    }
+
+    /// Saves every jar at least once every `max_wait` while its cookies keep changing.
+    pub fn with_save_max_wait(mut self, max_wait: Duration) -> Self {
+        self.save_max_wait = max_wait;
+        self
+    }
+
+    /// Encrypts every persisted jar with `cipher`.
+    #[cfg(feature = "cookies-encryption")]
+    pub fn with_cipher(mut self, cipher: Option<CookieCipher>) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    /// Returns the jar called `name`, loading it from disk if needed.
+    pub fn get(&self, name: &str) -> Arc<CookieStoreMutex> {
+        self.jars
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(self.open(name)))
+            .clone()
+    }
+
+    fn open(&self, name: &str) -> CookieStoreMutex {
+        let path = (!self.ephemeral.contains(name)).then(|| self.dir.join(jar_filename(name)));
+        let store = path
+            .as_deref()
+            .map(|path| self.load(path))
+            .unwrap_or_default();
+        let jar = CookieStoreMutex::new(path.clone(), store)
+            .with_save_debounce(self.save_debounce)
+            .with_save_max_wait(self.save_max_wait)
+            .with_events(self.events.as_ref().map(|events| events.for_jar(name)));
+        #[cfg(feature = "cookies-encryption")]
+        let jar = jar.with_cipher(self.cipher.clone());
+
+        #[cfg(feature = "cookies-encryption")]
+        if self.needs_migration(path.as_deref()) {
+            // rewrite the legacy plaintext file in the encrypted format right away
+            if let Err(_e) = jar.request_save() {
+                #[cfg(feature = "tracing")]
+                tracing::error!("failed to migrate cookie jar: {_e}");
+            }
+        }
+
+        jar
+    }
+
+    /// Loads a persisted jar, trying its backup when the file itself is
+    /// unreadable, and falling back to an empty jar when both are.
+    fn load(&self, path: &Path) -> CookieStore {
+        #[cfg(feature = "cookies-encryption")]
+        let plaintext_allowed = self.plaintext_allowed(path);
+        #[cfg(not(feature = "cookies-encryption"))]
+        let plaintext_allowed = true;
+
+        for candidate in jar_files(path) {
+            match self.read(&candidate, plaintext_allowed) {
+                Ok(Some(store)) => return store,
+                Ok(None) => {}
+                Err(_e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("failed to load cookie jar {}: {_e}", candidate.display());
+                }
+            }
+        }
+        Default::default()
+    }
+
+    /// Reads a single jar file, returning `None` if it doesn't exist.
+    ///
+    /// Unless `plaintext_allowed`, only encrypted files are accepted.
+    fn read(
+        &self,
+        path: &Path,
+        plaintext_allowed: bool,
+    ) -> cookie_store::Result<Option<CookieStore>> {
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        // older versions created the file before anything was saved
+        if data.is_empty() {
+            return Ok(Some(Default::default()));
+        }
+
+        #[cfg(feature = "cookies-encryption")]
+        let data = CookieCipher::decrypt_file(self.cipher.as_ref(), data, plaintext_allowed)?;
+        #[cfg(not(feature = "cookies-encryption"))]
+        let _ = plaintext_allowed;
+
+        load_store(data.as_slice()).map(Some)
+    }
+
+    /// Plaintext files are only trusted until the jar was first encrypted, so a
+    /// plaintext file dropped next to an encrypted jar is never loaded.
+    #[cfg(feature = "cookies-encryption")]
+    fn plaintext_allowed(&self, path: &Path) -> bool {
+        self.cipher.is_none()
+            || !jar_files(path)
+                .iter()
+                .any(|file| file_starts_with(file, ENCRYPTED_MAGIC))
+    }
+
+    /// Whether a legacy plaintext jar is being loaded with a key, and must be
+    /// rewritten encrypted.
+    #[cfg(feature = "cookies-encryption")]
+    fn needs_migration(&self, path: Option<&Path>) -> bool {
+        let (Some(_), Some(path)) = (&self.cipher, path) else {
+            return false;
+        };
+        self.plaintext_allowed(path)
+            && jar_files(path).iter().any(|file| {
+                std::fs::read(file)
+                    .map(|data| !data.is_empty() && !data.starts_with(ENCRYPTED_MAGIC))
+                    .unwrap_or(false)
+            })
+    }
+
+    /// Persists every loaded jar, blocking until all writes have finished.
+    pub fn save_all(&self) {
+        let jars = self
+            .jars
+            .lock()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        for jar in jars {
+            match jar.request_save() {
+                Ok(rx) => {
+                    let _ = rx.recv();
+                }
+                Err(_e) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("failed to save cookie jar: {_e}");
+                }
+            }
+        }
+    }
+}
+
+/// Maps a jar name to a file name, escaping anything that isn't safe in a path.
+fn jar_filename(name: &str) -> String {
+    if name == DEFAULT_COOKIE_JAR {
+        return crate::COOKIES_FILENAME.to_string();
+    }
+
+    let mut filename = format!("{}.", crate::COOKIES_FILENAME);
+    for byte in name.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+            filename.push(byte as char);
+        } else {
+            filename.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    filename
+}
+
+/// Marks an encrypted jar file, followed by the nonce and the ciphertext.
+#[cfg(feature = "cookies-encryption")]
+const ENCRYPTED_MAGIC: &[u8] = b"CORSFETCH-JAR-V1\n";
+
+/// Encrypts persisted cookie jars with ChaCha20-Poly1305.
+#[cfg(feature = "cookies-encryption")]
+#[derive(Clone)]
+pub struct CookieCipher(Arc<ChaCha20Poly1305>);
+
+#[cfg(feature = "cookies-encryption")]
+impl std::fmt::Debug for CookieCipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CookieCipher(..)")
+    }
+}
+
+#[cfg(feature = "cookies-encryption")]
+impl CookieCipher {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self(Arc::new(ChaCha20Poly1305::new(Key::from_slice(&key))))
+    }
+
+    fn encrypt(&self, plaintext: &[u8]) -> cookie_store::Result<Vec<u8>> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .0
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| "failed to encrypt cookie jar")?;
+
+        let mut data = ENCRYPTED_MAGIC.to_vec();
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+        Ok(data)
+    }
+
+    /// Returns the plaintext of a jar file, which may be a legacy plaintext jar
+    /// if `plaintext_allowed`.
+    fn decrypt_file(
+        cipher: Option<&Self>,
+        data: Vec<u8>,
+        plaintext_allowed: bool,
+    ) -> cookie_store::Result<Vec<u8>> {
+        let Some(data) = data.strip_prefix(ENCRYPTED_MAGIC) else {
+            if cipher.is_some() && !plaintext_allowed {
+                return Err("refusing a plaintext cookie jar next to an encrypted one".into());
+            }
+            return Ok(data);
+        };
+        let Some(cipher) = cipher else {
+            return Err("cookie jar is encrypted but no key was configured".into());
+        };
+        if data.len() < 12 {
+            return Err("encrypted cookie jar is truncated".into());
+        }
+
+        let (nonce, ciphertext) = data.split_at(12);
+        let plaintext = cipher
+            .0
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "wrong key or corrupted encrypted cookie jar")?;
+        Ok(plaintext)
+    }
+}
+
 #[derive(Debug)]
 struct CancellableTask(tauri::async_runtime::JoinHandle<()>);
 
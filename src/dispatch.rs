@@ -0,0 +1,155 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::sync::Arc;
+#[cfg(feature = "http3")]
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    limiter::{Limiter, Permit, Priority},
+    rate_limit::TokenBucket,
+    Result, RetryPolicy,
+};
+
+/// Sends a request through the concurrency limit, the rate limit and the retry
+/// policy that apply to it, for both webview and Rust requests.
+pub(crate) struct Dispatch {
+    pub(crate) retry: Option<RetryPolicy>,
+    pub(crate) limiter: Option<Arc<Limiter>>,
+    pub(crate) rate_limit: Option<Arc<TokenBucket>>,
+    pub(crate) host: String,
+    pub(crate) priority: Priority,
+    #[cfg(feature = "http3")]
+    pub(crate) http3: Http3,
+    /// The app's proxy auto-config and the URL to resolve with it.
+    #[cfg(feature = "pac")]
+    pub(crate) pac: Option<(Arc<crate::pac::PacResolver>, url::Url)>,
+}
+
+impl Dispatch {
+    #[cfg(feature = "http3")]
+    pub(crate) fn http3(mut self, http3: bool) -> Self {
+        self.http3.enabled = http3;
+        self
+    }
+
+    /// Skips the proxy auto-config, for requests with their own proxy or transport.
+    #[cfg_attr(not(feature = "pac"), allow(unused_mut))]
+    pub(crate) fn without_pac(mut self) -> Self {
+        #[cfg(feature = "pac")]
+        {
+            self.pac = None;
+        }
+        self
+    }
+
+    /// Sends `request`, returning its response and the concurrency slot it holds
+    /// until the body is read.
+    pub(crate) async fn send(
+        self,
+        mut request: reqwest::RequestBuilder,
+    ) -> Result<(reqwest::Response, Option<Permit>)> {
+        // evaluated off the async runtime, since reqwest looks the proxy up synchronously
+        #[cfg(feature = "pac")]
+        if let Some((pac, url)) = &self.pac {
+            pac.resolve(url).await?;
+        }
+
+        let permit = match &self.limiter {
+            Some(limiter) => Some(limiter.acquire(&self.host, self.priority).await),
+            None => None,
+        };
+        let mut attempt = 1;
+        loop {
+            // every attempt counts against the rate limit, but keeps its slot. Tokens
+            // are only taken once the slot is, so requests leaving the queue together
+            // are still spread out by the rate limit
+            if let Some(bucket) = &self.rate_limit {
+                bucket.acquire().await?;
+            }
+
+            let next = self.retry.as_ref().and_then(|_| request.try_clone());
+            let result = self.attempt(request).await;
+            let delay = self
+                .retry
+                .as_ref()
+                .and_then(|policy| policy.delay(attempt, &result));
+            match (delay, next) {
+                (Some(delay), Some(next)) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!("retrying request in {delay:?} (attempt {attempt})");
+                    drop(result);
+                    tokio::time::sleep(delay).await;
+                    request = next;
+                    attempt += 1;
+                }
+                _ => return Ok((result?, permit)),
+            }
+        }
+    }
+
+    async fn attempt(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> reqwest::Result<reqwest::Response> {
+        #[cfg(feature = "http3")]
+        if self.http3.enabled && !self.http3.failures.contains(&self.http3.origin) {
+            let fallback = self.http3.fallback.then(|| request.try_clone()).flatten();
+            return match request.version(http::Version::HTTP_3).send().await {
+                // only a QUIC connection that failed, before anything was sent, falls back
+                Err(e) if e.is_connect() => {
+                    self.http3.failures.insert(self.http3.origin.clone());
+                    match fallback {
+                        Some(fallback) => {
+                            #[cfg(feature = "tracing")]
+                            tracing::debug!("HTTP/3 failed, falling back to TCP: {e}");
+                            fallback.send().await
+                        }
+                        None => Err(e),
+                    }
+                }
+                result => result,
+            };
+        }
+        request.send().await
+    }
+}
+
+/// Tries HTTP/3 first, falling back to TCP when the QUIC connection fails.
+#[cfg(feature = "http3")]
+pub(crate) struct Http3 {
+    pub(crate) enabled: bool,
+    pub(crate) origin: url::Origin,
+    /// Whether the request may be sent again over TCP, following the idempotency
+    /// rules of retries.
+    pub(crate) fallback: bool,
+    pub(crate) failures: Arc<Http3Failures>,
+}
+
+/// How long an origin whose QUIC connection failed goes straight to TCP, so
+/// networks that block UDP don't wait for the QUIC timeout on every request.
+#[cfg(feature = "http3")]
+const HTTP3_FAILURE_TTL: Duration = Duration::from_secs(300);
+
+/// The origins whose QUIC connection recently failed.
+#[cfg(feature = "http3")]
+#[derive(Debug, Default)]
+pub(crate) struct Http3Failures(Mutex<HashMap<url::Origin, Instant>>);
+
+#[cfg(feature = "http3")]
+impl Http3Failures {
+    fn contains(&self, origin: &url::Origin) -> bool {
+        let mut failures = self.0.lock().unwrap();
+        failures.retain(|_, at| at.elapsed() < HTTP3_FAILURE_TTL);
+        failures.contains_key(origin)
+    }
+
+    fn insert(&self, origin: url::Origin) {
+        self.0.lock().unwrap().insert(origin, Instant::now());
+    }
+}
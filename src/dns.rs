@@ -0,0 +1,54 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+use reqwest::dns::Resolve;
+
+use crate::{Error, Result};
+
+/// DNS settings of every client built by the plugin.
+#[derive(Clone, Default)]
+pub(crate) struct DnsConfig {
+    pub(crate) overrides: HashMap<String, Vec<SocketAddr>>,
+    pub(crate) resolver: Option<Arc<dyn Resolve>>,
+}
+
+impl DnsConfig {
+    pub(crate) fn apply(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        if let Some(resolver) = &self.resolver {
+            builder = builder.dns_resolver(resolver.clone());
+        }
+        for (host, addrs) in &self.overrides {
+            builder = builder.resolve_to_addrs(host, addrs);
+        }
+        builder
+    }
+}
+
+/// Applies the `resolve` fetch option, mapping hosts to `ip` or `ip:port` addresses.
+pub(crate) fn resolve(
+    mut builder: reqwest::ClientBuilder,
+    overrides: HashMap<String, Vec<String>>,
+) -> Result<reqwest::ClientBuilder> {
+    for (host, addrs) in overrides {
+        let addrs = addrs
+            .iter()
+            .map(|addr| parse_addr(addr))
+            .collect::<Result<Vec<_>>>()?;
+        builder = builder.resolve_to_addrs(&host, &addrs);
+    }
+    Ok(builder)
+}
+
+/// Parses `ip:port`, or a bare `ip` using the default port of the scheme.
+fn parse_addr(addr: &str) -> Result<SocketAddr> {
+    addr.parse::<SocketAddr>()
+        .or_else(|_| addr.parse::<IpAddr>().map(|ip| SocketAddr::new(ip, 0)))
+        .map_err(|_| Error::InvalidAddress(addr.to_string()))
+}
@@ -22,14 +22,44 @@ pub enum Error {
     UrlParseError(#[from] url::ParseError),
     #[error(transparent)]
     HttpMethod(#[from] http::method::InvalidMethod),
+    #[error(transparent)]
+    HttpStatus(#[from] http::status::InvalidStatusCode),
+    #[error("the app doesn't allow requests to set their own proxy")]
+    ProxyNotAllowed,
+    #[error("the app doesn't allow requests over the unix socket {0}")]
+    UnixSocketNotAllowed(String),
+    #[error("the app doesn't allow this webview to use the cookie jar {0}")]
+    CookieJarNotAllowed(String),
+    #[error("the proxy auto-config script didn't pick a proxy in time")]
+    PacTimeout,
+    #[error("no proxy profile named {0}")]
+    UnknownProxyProfile(String),
+    #[error("the `{0}` option requires the `{1}` feature of the plugin")]
+    FeatureNotEnabled(&'static str, &'static str),
+    #[error("SOCKS proxies require the `socks` feature of the plugin")]
+    SocksNotEnabled,
     #[error("scheme {0} not supported")]
     SchemeNotSupport(String),
     #[error("User cancelled the request")]
     RequestCanceled,
+    #[error("invalid address {0}, expected an IP with an optional port")]
+    InvalidAddress(String),
     #[error("failed to process data url")]
     DataUrlError,
     #[error("failed to decode data url into bytes")]
     DataUrlDecodeError,
+    #[error("rate limited, retry after {}ms", .0.as_millis())]
+    RateLimited(std::time::Duration),
+    #[error("response body exceeds {0} bytes")]
+    ResponseTooLarge(u64),
+    #[error("response compression ratio exceeds {0}")]
+    CompressionRatioExceeded(f64),
+    #[error("response body does not match its integrity metadata")]
+    IntegrityMismatch,
+    #[error("response body can no longer be read after an error")]
+    BodyClosed,
+    #[error("failed to import cookies: {0}")]
+    CookieImport(String),
     #[error(transparent)]
     Tauri(#[from] tauri::Error),
     #[error(transparent)]
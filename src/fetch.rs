@@ -0,0 +1,137 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use bytes::Bytes;
+use http::{HeaderMap, HeaderName, HeaderValue, Method};
+
+use crate::{limiter::Priority, Http, Result, RetryPolicy};
+
+/// A request made from Rust with the plugin's configuration, see [`crate::CorsFetch::request`].
+///
+/// Unlike webview requests, the response body is returned as is: the body size
+/// limits and the cookie bridge only apply to the webview.
+pub struct FetchBuilder<'a> {
+    http: &'a Http,
+    method: Method,
+    url: url::Url,
+    headers: HeaderMap,
+    body: Option<Bytes>,
+    #[cfg(feature = "cookies")]
+    cookie_jar: Option<String>,
+    #[cfg(feature = "cookies")]
+    cookies: bool,
+    retry: Option<RetryPolicy>,
+    priority: Priority,
+}
+
+impl<'a> FetchBuilder<'a> {
+    pub(crate) fn new(http: &'a Http, method: Method, url: url::Url) -> Self {
+        Self {
+            http,
+            method,
+            url,
+            headers: HeaderMap::new(),
+            body: None,
+            #[cfg(feature = "cookies")]
+            cookie_jar: None,
+            #[cfg(feature = "cookies")]
+            cookies: true,
+            retry: None,
+            priority: Priority::default(),
+        }
+    }
+
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    pub fn headers(mut self, headers: HeaderMap) -> Self {
+        self.headers.extend(headers);
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Uses the cookie jar called `jar` instead of the default one.
+    #[cfg(feature = "cookies")]
+    pub fn cookie_jar(mut self, jar: impl Into<String>) -> Self {
+        self.cookie_jar = Some(jar.into());
+        self
+    }
+
+    /// Neither sends nor stores cookies.
+    #[cfg(feature = "cookies")]
+    pub fn without_cookies(mut self) -> Self {
+        self.cookies = false;
+        self
+    }
+
+    /// Retries the request with `policy` instead of the [`crate::Builder::retry`] one.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Orders the request in the concurrency limit queue.
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sends the request.
+    ///
+    /// The request holds its concurrency slot until the response body is read or dropped.
+    pub async fn send(self) -> Result<reqwest::Response> {
+        let Self {
+            http,
+            method,
+            url,
+            mut headers,
+            body,
+            #[cfg(feature = "cookies")]
+            cookie_jar,
+            #[cfg(feature = "cookies")]
+            cookies,
+            retry,
+            priority,
+        } = self;
+
+        #[allow(unused_mut)]
+        let mut client = http.request_client(http.new_client_builder(), false);
+        #[cfg(feature = "cookies")]
+        if cookies {
+            let jar = cookie_jar
+                .as_deref()
+                .unwrap_or(crate::cookies::DEFAULT_COOKIE_JAR);
+            client.cookie_jar(http.cookie_jars.get(jar));
+        }
+        let client = client.build()?;
+
+        if let Some(policy) = http.origin_policy(&url) {
+            policy.apply(&mut headers, &url)?;
+        }
+        client.add_cookies(&url, &mut headers);
+
+        let mut request = client.request(method.clone(), url.clone()).headers(headers);
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::trace!("{:?}", request);
+
+        let retry = retry.or_else(|| http.retry.clone());
+        let dispatch = http.dispatch(&method, &url, retry, priority);
+        let (res, permit) = dispatch.send(request).await?;
+        client.store_cookies(&res);
+        Ok(match permit {
+            Some(permit) => permit.hold(res),
+            None => res,
+        })
+    }
+}
@@ -0,0 +1,54 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+/// A host matcher used to scope per-host [`crate::Builder`] policies.
+///
+/// - `api.example.com` matches that host only.
+/// - `*.example.com` matches `example.com` and any of its subdomains.
+/// - `*` matches every host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostPattern(String);
+
+impl HostPattern {
+    pub fn new(pattern: impl Into<String>) -> Self {
+        Self(pattern.into().to_ascii_lowercase())
+    }
+
+    pub fn matches(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        match self.0.as_str() {
+            "*" => true,
+            pattern => match pattern.strip_prefix("*.") {
+                Some(domain) => {
+                    host == domain
+                        || host
+                            .strip_suffix(domain)
+                            .is_some_and(|sub| sub.ends_with('.'))
+                }
+                None => host == pattern,
+            },
+        }
+    }
+}
+
+impl From<&str> for HostPattern {
+    fn from(pattern: &str) -> Self {
+        Self::new(pattern)
+    }
+}
+
+impl From<String> for HostPattern {
+    fn from(pattern: String) -> Self {
+        Self::new(pattern)
+    }
+}
+
+/// Returns the value of the first rule whose pattern matches the host of `url`.
+pub(crate) fn lookup<'a, T>(rules: &'a [(HostPattern, T)], url: &url::Url) -> Option<&'a T> {
+    let host = url.host_str()?;
+    rules
+        .iter()
+        .find(|(pattern, _)| pattern.matches(host))
+        .map(|(_, value)| value)
+}
@@ -0,0 +1,188 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use base64::{engine::general_purpose, Engine};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::{Error, Result};
+
+/// Checks a response body against the fetch `integrity` option, hashing it as it is read.
+///
+/// See <https://www.w3.org/TR/SRI/>.
+pub(crate) struct Integrity {
+    hasher: Hasher,
+    /// The expected digests for the strongest algorithm in the metadata.
+    digests: Vec<Vec<u8>>,
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Sha384(Sha384),
+    Sha512(Sha512),
+}
+
+impl Hasher {
+    fn new(algorithm: &str) -> Option<Self> {
+        match algorithm {
+            "sha256" => Some(Self::Sha256(Sha256::new())),
+            "sha384" => Some(Self::Sha384(Sha384::new())),
+            "sha512" => Some(Self::Sha512(Sha512::new())),
+            _ => None,
+        }
+    }
+
+    fn strength(&self) -> u8 {
+        match self {
+            Self::Sha256(_) => 1,
+            Self::Sha384(_) => 2,
+            Self::Sha512(_) => 3,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Sha384(hasher) => hasher.update(data),
+            Self::Sha512(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::Sha256(hasher) => hasher.finalize().to_vec(),
+            Self::Sha384(hasher) => hasher.finalize().to_vec(),
+            Self::Sha512(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+impl Integrity {
+    /// Parses integrity metadata like `sha384-<base64> sha512-<base64>`.
+    ///
+    /// Like browsers, unknown algorithms and malformed entries are ignored, and
+    /// `None` is returned when nothing is left to check.
+    pub(crate) fn parse(metadata: &str) -> Option<Self> {
+        let mut integrity: Option<Self> = None;
+        for entry in metadata.split_ascii_whitespace() {
+            // options after `?` are reserved and ignored
+            let entry = entry.split('?').next().unwrap_or_default();
+            let Some((algorithm, digest)) = entry.split_once('-') else {
+                continue;
+            };
+            let Some(hasher) = Hasher::new(&algorithm.to_ascii_lowercase()) else {
+                continue;
+            };
+            let Ok(digest) = general_purpose::STANDARD
+                .decode(digest)
+                .or_else(|_| general_purpose::URL_SAFE.decode(digest))
+            else {
+                continue;
+            };
+
+            match &mut integrity {
+                Some(current) if current.hasher.strength() == hasher.strength() => {
+                    current.digests.push(digest);
+                }
+                Some(current) if current.hasher.strength() > hasher.strength() => {}
+                _ => {
+                    integrity = Some(Self {
+                        hasher,
+                        digests: vec![digest],
+                    })
+                }
+            }
+        }
+        integrity
+    }
+
+    pub(crate) fn update(&mut self, chunk: &[u8]) {
+        self.hasher.update(chunk);
+    }
+
+    /// Checks the hash of everything read so far against the expected digests.
+    pub(crate) fn verify(self) -> Result<()> {
+        let digest = self.hasher.finalize();
+        if self.digests.contains(&digest) {
+            Ok(())
+        } else {
+            Err(Error::IntegrityMismatch)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &[u8] = b"alert('hello')";
+
+    fn metadata<D: Digest>(algorithm: &str, body: &[u8]) -> String {
+        format!(
+            "{algorithm}-{}",
+            general_purpose::STANDARD.encode(D::digest(body))
+        )
+    }
+
+    fn check(metadata: &str, chunks: &[&[u8]]) -> Result<()> {
+        let mut integrity = Integrity::parse(metadata).expect("integrity metadata");
+        for chunk in chunks {
+            integrity.update(chunk);
+        }
+        integrity.verify()
+    }
+
+    #[test]
+    fn matching_body_verifies_across_chunks() {
+        let metadata = metadata::<Sha384>("sha384", BODY);
+        assert!(check(&metadata, &[BODY]).is_ok());
+        assert!(check(&metadata, &[&BODY[..5], &BODY[5..]]).is_ok());
+        assert!(matches!(
+            check(&metadata, &[b"tampered"]),
+            Err(Error::IntegrityMismatch)
+        ));
+    }
+
+    #[test]
+    fn strongest_algorithm_wins() {
+        // the sha256 digest matches, but only the sha512 one counts
+        let metadata = format!(
+            "{} {} {}",
+            metadata::<Sha256>("sha256", BODY),
+            metadata::<Sha512>("sha512", b"other"),
+            metadata::<Sha384>("sha384", BODY),
+        );
+        let integrity = Integrity::parse(&metadata).unwrap();
+        assert_eq!(integrity.hasher.strength(), 3);
+        assert!(check(&metadata, &[BODY]).is_err());
+    }
+
+    #[test]
+    fn any_digest_of_the_strongest_algorithm_matches() {
+        let metadata = format!(
+            "{} {}",
+            metadata::<Sha256>("sha256", b"other"),
+            metadata::<Sha256>("SHA256", BODY),
+        );
+        assert_eq!(Integrity::parse(&metadata).unwrap().digests.len(), 2);
+        assert!(check(&metadata, &[BODY]).is_ok());
+    }
+
+    #[test]
+    fn unknown_and_malformed_entries_are_ignored() {
+        assert!(Integrity::parse("").is_none());
+        assert!(Integrity::parse("md5-abc sha1-abc sha256 sha256-!!!").is_none());
+
+        let metadata = format!(
+            "md5-abc {}?ct=text/javascript sha512-!!!",
+            metadata::<Sha384>("sha384", BODY)
+        );
+        assert!(check(&metadata, &[BODY]).is_ok());
+    }
+
+    #[test]
+    fn url_safe_base64_is_accepted() {
+        let digest = general_purpose::URL_SAFE.encode(Sha256::digest(BODY));
+        assert!(check(&format!("sha256-{digest}"), &[BODY]).is_ok());
+    }
+}
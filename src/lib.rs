@@ -7,83 +7,742 @@
 //! Enabling Cross-Origin Resource Sharing (CORS) for Fetch Requests within Tauri applications.
 
 pub use reqwest;
-use tauri::{
-    plugin::{Builder, TauriPlugin},
-    Manager, Runtime,
-};
+use std::collections::HashMap;
+#[cfg(feature = "cookies")]
+use std::collections::HashSet;
+use tauri::{plugin::TauriPlugin, AppHandle, Manager, Runtime};
+
+use crate::commands::Credentials;
 
+#[cfg(feature = "cookies")]
+pub use bridge::{CookieBridge, CookieSyncDirection};
+#[cfg(feature = "cookies")]
+pub use cookie_events::{CookieChange, CookieChangeCause, COOKIE_CHANGE_EVENT};
+#[cfg(feature = "cookies")]
+pub use cookie_formats::{CookieFormat, CookieImportMode, CookieRecord};
 pub use error::{Error, Result};
+pub use fetch::FetchBuilder;
+pub use host::HostPattern;
+pub use limiter::Priority;
+pub use origin::{HeaderRule, OriginPolicy};
+#[cfg(feature = "pac")]
+pub use proxy::PacSource;
+pub use proxy::ProxyMode;
+pub use rate_limit::{RateLimit, RateLimitMode};
+pub use retry::RetryPolicy;
+mod body;
+#[cfg(feature = "cookies")]
+mod bridge;
+mod client;
 mod commands;
 #[cfg(feature = "cookies")]
+mod cookie_events;
+#[cfg(feature = "cookies")]
+mod cookie_formats;
+#[cfg(feature = "cookies")]
 mod cookies;
+mod dispatch;
+mod dns;
 mod error;
+mod fetch;
+mod host;
+mod integrity;
+mod limiter;
+mod origin;
+#[cfg(feature = "pac")]
+mod pac;
+mod proxy;
+mod rate_limit;
+mod retry;
+mod unix_socket;
 
 #[cfg(feature = "cookies")]
-const COOKIES_FILENAME: &str = ".cookies";
+pub(crate) const COOKIES_FILENAME: &str = ".cookies";
 
 pub(crate) struct Http {
     #[cfg(feature = "cookies")]
-    cookies_jar: std::sync::Arc<crate::cookies::CookieStoreMutex>,
+    cookie_jars: crate::cookies::CookieJars,
+    #[cfg(feature = "cookies")]
+    webview_cookie_jars: HashMap<String, String>,
+    #[cfg(feature = "cookies")]
+    allowed_webview_cookie_jars: HashMap<String, HashSet<String>>,
+    #[cfg(feature = "cookies")]
+    cookie_jar_per_webview: bool,
+    #[cfg(feature = "cookies")]
+    cookie_bridge: Option<CookieBridge>,
+    origin_policies: Vec<(HostPattern, OriginPolicy)>,
+    credentials_origins: Vec<url::Origin>,
+    retry: Option<RetryPolicy>,
+    limiter: Option<std::sync::Arc<limiter::Limiter>>,
+    #[cfg(feature = "http3")]
+    http3_failures: std::sync::Arc<dispatch::Http3Failures>,
+    rate_limits: Vec<(HostPattern, std::sync::Arc<rate_limit::TokenBucket>)>,
+    body_limits: body::BodyLimits,
+    client_builder: Option<client::ClientBuilderHook>,
+    client: Option<reqwest::Client>,
+    dns: dns::DnsConfig,
+    proxy: proxy::AppProxy,
+    allows_request_proxy: bool,
+    proxy_profiles: proxy::ProxyProfiles,
+    #[cfg(unix)]
+    unix_sockets: Vec<std::path::PathBuf>,
 }
 
-pub fn init<R: Runtime>() -> TauriPlugin<R> {
-    Builder::<R>::new("cors-fetch")
-        .setup(|app, _| {
+impl Http {
+    fn new<R: Runtime>(app: &AppHandle<R>, builder: Builder) -> Result<Self> {
+        #[cfg(feature = "cookies")]
+        let cookie_jars = {
+            let events = cookie_events(app, &builder);
+            let cache_dir = app.path().app_cache_dir()?;
+            std::fs::create_dir_all(&cache_dir)?;
+            crate::cookies::CookieJars::new(cache_dir, builder.ephemeral_cookie_jars)
+                .with_save_debounce(
+                    builder
+                        .cookie_save_debounce
+                        .unwrap_or(crate::cookies::DEFAULT_SAVE_DEBOUNCE),
+                )
+                .with_save_max_wait(
+                    builder
+                        .cookie_save_max_wait
+                        .unwrap_or(crate::cookies::DEFAULT_SAVE_MAX_WAIT),
+                )
+                .with_events(events)
+        };
+        #[cfg(feature = "cookies-encryption")]
+        let cookie_jars = cookie_jars.with_cipher(
+            builder
+                .cookie_jar_key
+                .map(crate::cookies::CookieCipher::new),
+        );
+        #[cfg(not(feature = "cookies"))]
+        let _ = app;
+
+        Ok(Self {
+            #[cfg(feature = "cookies")]
+            cookie_jars,
             #[cfg(feature = "cookies")]
+            webview_cookie_jars: builder.webview_cookie_jars,
+            #[cfg(feature = "cookies")]
+            allowed_webview_cookie_jars: builder.allowed_webview_cookie_jars,
+            #[cfg(feature = "cookies")]
+            cookie_jar_per_webview: builder.cookie_jar_per_webview,
+            #[cfg(feature = "cookies")]
+            cookie_bridge: builder.cookie_bridge,
+            origin_policies: builder.origin_policies,
+            credentials_origins: builder.credentials_origins,
+            retry: builder.retry,
+            limiter: limiter::Limiter::new(
+                builder.max_concurrent_requests,
+                builder.max_concurrent_requests_per_host,
+            ),
+            #[cfg(feature = "http3")]
+            http3_failures: Default::default(),
+            rate_limits: builder
+                .rate_limits
+                .into_iter()
+                .map(|(host, limit)| (host, rate_limit::TokenBucket::new(limit).into()))
+                .collect(),
+            body_limits: body::BodyLimits {
+                max_bytes: builder.max_response_bytes,
+                max_compression_ratio: builder.max_compression_ratio,
+            },
+            client_builder: builder.client_builder,
+            client: builder.client,
+            dns: builder.dns,
+            allows_request_proxy: builder.proxy_mode.is_none() || builder.allow_request_proxy,
+            proxy: builder
+                .proxy_mode
+                .map(proxy::AppProxy::new)
+                .unwrap_or_default(),
+            proxy_profiles: proxy::ProxyProfiles::new(builder.proxy_profiles),
+            #[cfg(unix)]
+            unix_sockets: builder.unix_sockets,
+        })
+    }
+
+    pub(crate) fn origin_policy(&self, url: &url::Url) -> Option<&OriginPolicy> {
+        host::lookup(&self.origin_policies, url)
+    }
+
+    #[cfg_attr(not(feature = "cookies"), allow(dead_code))]
+    pub(crate) fn allows_credentials(&self, credentials: Credentials, url: &url::Url) -> bool {
+        match credentials {
+            Credentials::Omit => false,
+            Credentials::SameOrigin => self.credentials_origins.contains(&url.origin()),
+            Credentials::Include => true,
+        }
+    }
+
+    /// The retry policy of a request, the `retry` fetch option overriding the Builder default.
+    pub(crate) fn retry_policy(
+        &self,
+        config: Option<crate::retry::RetryConfig>,
+    ) -> Result<Option<RetryPolicy>> {
+        match config {
+            Some(config) => config.resolve(self.retry.as_ref()).map(Some),
+            None => Ok(self.retry.clone()),
+        }
+    }
+
+    /// A client builder with the app-wide settings, for the request to add its own.
+    pub(crate) fn new_client_builder(&self) -> reqwest::ClientBuilder {
+        let builder = self.proxy.apply(reqwest::ClientBuilder::new());
+        self.dns.apply(builder)
+    }
+
+    /// Whether the fetch `proxy` option may replace the [`Builder::proxy_mode`].
+    pub(crate) fn allows_request_proxy(&self) -> bool {
+        self.allows_request_proxy
+    }
+
+    /// Sends every request of `builder` over the Unix socket at `path`, if it
+    /// was allowed with [`Builder::allow_unix_socket`].
+    pub(crate) fn unix_socket(
+        &self,
+        builder: reqwest::ClientBuilder,
+        path: &std::path::Path,
+    ) -> Result<reqwest::ClientBuilder> {
+        #[cfg(unix)]
+        if self.unix_sockets.iter().any(|allowed| allowed == path) {
+            return Ok(builder.unix_socket(path));
+        }
+        #[cfg(not(unix))]
+        let _ = builder;
+        Err(Error::UnixSocketNotAllowed(path.display().to_string()))
+    }
+
+    /// The proxy profile called `name`, see [`Builder::proxy_profile`].
+    pub(crate) fn proxy_profile(&self, name: &str) -> Result<reqwest::Proxy> {
+        self.proxy_profiles.get(name)
+    }
+
+    /// The client of a request, built from `builder` with the request's settings.
+    ///
+    /// Requests that have no client settings of their own (`overrides`) use the
+    /// app's prebuilt client, if any.
+    pub(crate) fn request_client(
+        &self,
+        builder: reqwest::ClientBuilder,
+        overrides: bool,
+    ) -> client::RequestClient {
+        client::RequestClient::new(
+            builder,
+            self.client.clone().filter(|_| !overrides),
+            self.client_builder.clone(),
+        )
+    }
+
+    /// How a request to `url` is sent, given its resolved retry policy.
+    pub(crate) fn dispatch(
+        &self,
+        method: &http::Method,
+        url: &url::Url,
+        retry: Option<RetryPolicy>,
+        priority: Priority,
+    ) -> dispatch::Dispatch {
+        dispatch::Dispatch {
+            #[cfg(feature = "http3")]
+            http3: dispatch::Http3 {
+                enabled: false,
+                origin: url.origin(),
+                fallback: retry
+                    .as_ref()
+                    .map_or(method.is_idempotent(), |policy| policy.resends(method)),
+                failures: self.http3_failures.clone(),
+            },
+            retry: retry.filter(|policy| policy.allows(method)),
+            limiter: self.limiter.clone(),
+            rate_limit: host::lookup(&self.rate_limits, url).cloned(),
+            host: url.host_str().unwrap_or_default().to_string(),
+            priority,
+            #[cfg(feature = "pac")]
+            pac: self.proxy.pac().map(|pac| (pac.clone(), url.clone())),
+        }
+    }
+
+    /// The body limits of a request, the stricter of the fetch options and the Builder.
+    pub(crate) fn body_limits(&self, requested: body::BodyLimits) -> body::BodyLimits {
+        self.body_limits.min(requested)
+    }
+
+    /// Picks the cookie jar for a webview request: the jar mapped to the webview,
+    /// then the per-webview jar, then the default one.
+    ///
+    /// The page may only pick another jar with `cookieJar` if it was allowed for the
+    /// webview with [`Builder::allow_webview_cookie_jar`], so it can't use the jars of
+    /// other webviews or create new ones.
+    #[cfg(feature = "cookies")]
+    pub(crate) fn cookie_jar(
+        &self,
+        webview_label: &str,
+        requested: Option<&str>,
+    ) -> Result<std::sync::Arc<crate::cookies::CookieStoreMutex>> {
+        let assigned = self
+            .webview_cookie_jars
+            .get(webview_label)
+            .map(String::as_str)
+            .or(self.cookie_jar_per_webview.then_some(webview_label))
+            .unwrap_or(crate::cookies::DEFAULT_COOKIE_JAR);
+        let name = match requested {
+            None => assigned,
+            Some(jar)
+                if jar == assigned
+                    || self
+                        .allowed_webview_cookie_jars
+                        .get(webview_label)
+                        .is_some_and(|allowed| allowed.contains(jar)) =>
             {
-                let cookies_jar = {
-                    use crate::cookies::*;
-                    use std::fs::File;
-                    use std::io::BufReader;
-
-                    let cache_dir = app.path().app_cache_dir()?;
-                    std::fs::create_dir_all(&cache_dir)?;
-
-                    let path = cache_dir.join(COOKIES_FILENAME);
-                    let file = File::options()
-                        .create(true)
-                        .append(true)
-                        .read(true)
-                        .open(&path)?;
-
-                    let reader = BufReader::new(file);
-                    CookieStoreMutex::load(path.clone(), reader)
-                        .unwrap_or_else(|_e| CookieStoreMutex::new(path, Default::default()))
-                };
-
-                let state = Http {
-                    cookies_jar: std::sync::Arc::new(cookies_jar),
-                };
+                jar
+            }
+            Some(jar) => return Err(Error::CookieJarNotAllowed(jar.to_string())),
+        };
+        Ok(self.cookie_jars.get(name))
+    }
 
-                app.manage(state);
+    #[cfg(feature = "cookies")]
+    pub(crate) fn cookie_bridge(&self) -> Option<&CookieBridge> {
+        self.cookie_bridge.as_ref()
+    }
+}
+
+/// Forwards cookie changes to the Builder callback and, if enabled, to the webviews.
+#[cfg(feature = "cookies")]
+fn cookie_events<R: Runtime>(
+    app: &AppHandle<R>,
+    builder: &Builder,
+) -> Option<crate::cookie_events::CookieEvents> {
+    use tauri::Emitter;
+
+    if builder.cookie_change_listener.is_none() && !builder.emit_cookie_events {
+        return None;
+    }
+
+    let app = app.clone();
+    let listener = builder.cookie_change_listener.clone();
+    let emit = builder.emit_cookie_events;
+    Some(crate::cookie_events::CookieEvents::new(
+        std::sync::Arc::new(move |change: &CookieChange| {
+            if let Some(listener) = &listener {
+                listener(change);
+            }
+            if emit {
+                if let Err(_e) = app.emit(COOKIE_CHANGE_EVENT, change) {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("failed to emit cookie change: {_e}");
+                }
             }
+        }),
+        builder.cookie_event_values,
+    ))
+}
 
-            Ok(())
-        })
-        .on_event(|app, event| {
-            #[cfg(feature = "cookies")]
-            {
-                if let tauri::RunEvent::Exit = event {
-                    let state = app.state::<Http>();
-
-                    match state.cookies_jar.request_save() {
-                        Ok(rx) => {
-                            let _ = rx.recv();
-                        }
-                        Err(_e) => {
-                            #[cfg(feature = "tracing")]
-                            tracing::error!("failed to save cookie jar: {_e}");
-                        }
+/// Extensions to [`tauri::App`], [`tauri::AppHandle`], [`tauri::Webview`] and the other
+/// [`Manager`] types to access the cors-fetch APIs from Rust.
+pub trait CorsFetchExt<R: Runtime> {
+    fn cors_fetch(&self) -> CorsFetch<'_>;
+}
+
+impl<R: Runtime, T: Manager<R>> CorsFetchExt<R> for T {
+    fn cors_fetch(&self) -> CorsFetch<'_> {
+        CorsFetch(self.state::<Http>().inner())
+    }
+}
+
+/// Access to the cors-fetch plugin from Rust, see [`CorsFetchExt`].
+pub struct CorsFetch<'a>(&'a Http);
+
+impl<'a> CorsFetch<'a> {
+    /// Starts a request that is sent like the ones from the webview: with the
+    /// default cookie jar, the origin policies, rate limits, concurrency limits
+    /// and retry policy configured on the [`Builder`].
+    pub fn request(&self, method: http::Method, url: url::Url) -> FetchBuilder<'a> {
+        FetchBuilder::new(self.0, method, url)
+    }
+
+    pub fn get(&self, url: url::Url) -> FetchBuilder<'a> {
+        self.request(http::Method::GET, url)
+    }
+
+    pub fn post(&self, url: url::Url) -> FetchBuilder<'a> {
+        self.request(http::Method::POST, url)
+    }
+
+    /// Registers or replaces the proxy profile called `name`, see [`Builder::proxy_profile`].
+    pub fn set_proxy_profile(&self, name: impl Into<String>, proxy: reqwest::Proxy) {
+        self.0.proxy_profiles.set(name.into(), proxy);
+    }
+
+    /// Removes the proxy profile called `name`, returning whether it existed.
+    pub fn remove_proxy_profile(&self, name: &str) -> bool {
+        self.0.proxy_profiles.remove(name)
+    }
+
+    /// Exports the cookie jar called `jar` (`"default"` unless configured otherwise),
+    /// keeping only cookies whose domain matches `domains`, or all of them if it is empty.
+    #[cfg(feature = "cookies")]
+    pub fn export_cookies(
+        &self,
+        jar: &str,
+        format: CookieFormat,
+        domains: &[HostPattern],
+    ) -> Result<String> {
+        self.0.cookie_jars.get(jar).export(format, domains)
+    }
+
+    /// Imports cookies into the jar called `jar`, returning how many were stored.
+    #[cfg(feature = "cookies")]
+    pub fn import_cookies(
+        &self,
+        jar: &str,
+        format: CookieFormat,
+        data: &str,
+        mode: CookieImportMode,
+        domains: &[HostPattern],
+    ) -> Result<usize> {
+        self.0
+            .cookie_jars
+            .get(jar)
+            .import(format, data, mode, domains)
+    }
+
+    /// Removes the cookies of the jar called `jar` whose domain matches `domains`,
+    /// or all of them if it is empty, returning how many were removed.
+    #[cfg(feature = "cookies")]
+    pub fn clear_cookies(&self, jar: &str, domains: &[HostPattern]) -> usize {
+        self.0.cookie_jars.get(jar).clear(domains)
+    }
+}
+
+/// Builder for the cors-fetch plugin.
+///
+/// Policies configured here live in Rust and apply to every proxied request,
+/// regardless of what the page script passes to `fetch`.
+#[derive(Default)]
+pub struct Builder {
+    #[cfg(feature = "cookies")]
+    webview_cookie_jars: HashMap<String, String>,
+    #[cfg(feature = "cookies")]
+    allowed_webview_cookie_jars: HashMap<String, HashSet<String>>,
+    #[cfg(feature = "cookies")]
+    cookie_jar_per_webview: bool,
+    #[cfg(feature = "cookies")]
+    ephemeral_cookie_jars: HashSet<String>,
+    #[cfg(feature = "cookies")]
+    cookie_save_debounce: Option<std::time::Duration>,
+    #[cfg(feature = "cookies")]
+    cookie_save_max_wait: Option<std::time::Duration>,
+    #[cfg(feature = "cookies")]
+    cookie_bridge: Option<CookieBridge>,
+    #[cfg(feature = "cookies")]
+    cookie_change_listener: Option<crate::cookie_events::CookieListener>,
+    #[cfg(feature = "cookies")]
+    emit_cookie_events: bool,
+    #[cfg(feature = "cookies")]
+    cookie_event_values: bool,
+    #[cfg(feature = "cookies-encryption")]
+    cookie_jar_key: Option<[u8; 32]>,
+    origin_policies: Vec<(HostPattern, OriginPolicy)>,
+    credentials_origins: Vec<url::Origin>,
+    retry: Option<RetryPolicy>,
+    max_concurrent_requests: Option<usize>,
+    max_concurrent_requests_per_host: Option<usize>,
+    rate_limits: Vec<(HostPattern, RateLimit)>,
+    max_response_bytes: Option<u64>,
+    max_compression_ratio: Option<f64>,
+    client_builder: Option<client::ClientBuilderHook>,
+    client: Option<reqwest::Client>,
+    dns: dns::DnsConfig,
+    proxy_mode: Option<ProxyMode>,
+    allow_request_proxy: bool,
+    proxy_profiles: HashMap<String, reqwest::Proxy>,
+    #[cfg(unix)]
+    unix_sockets: Vec<std::path::PathBuf>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rewrites the `Origin` and `Referer` headers sent to matching hosts.
+    ///
+    /// Policies are checked in registration order and the first match wins.
+    pub fn origin_policy(mut self, host: impl Into<HostPattern>, policy: OriginPolicy) -> Self {
+        self.origin_policies.push((host.into(), policy));
+        self
+    }
+
+    /// Treats `origin` as same-origin for requests made with `credentials: 'same-origin'`.
+    ///
+    /// The app itself is served from a custom protocol, so without this no remote
+    /// target would ever receive cookies from the jar in that mode.
+    pub fn credentials_origin(mut self, origin: url::Url) -> Self {
+        self.credentials_origins.push(origin.origin());
+        self
+    }
+
+    /// Retries failed requests with `policy`, unless a request sets its own `retry` option.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Limits the number of requests in flight at once, across all hosts.
+    ///
+    /// Requests over the limit are queued in order, and stay cancellable while queued.
+    /// A request is in flight until its response body is fully read or cancelled.
+    pub fn max_concurrent_requests(mut self, max: usize) -> Self {
+        self.max_concurrent_requests = Some(max);
+        self
+    }
+
+    /// Limits the number of requests in flight at once to a single host.
+    pub fn max_concurrent_requests_per_host(mut self, max: usize) -> Self {
+        self.max_concurrent_requests_per_host = Some(max);
+        self
+    }
+
+    /// Rate limits requests to matching hosts, checked before every attempt is sent.
+    ///
+    /// All hosts matching `host` share a single token bucket. Like origin
+    /// policies, limits are checked in registration order and the first match wins.
+    pub fn rate_limit(mut self, host: impl Into<HostPattern>, limit: RateLimit) -> Self {
+        self.rate_limits.push((host.into(), limit));
+        self
+    }
+
+    /// Aborts responses whose decoded body is larger than `max` bytes.
+    ///
+    /// The `maxResponseBytes` fetch option can only lower this limit.
+    pub fn max_response_bytes(mut self, max: u64) -> Self {
+        self.max_response_bytes = Some(max);
+        self
+    }
+
+    /// Aborts responses that decode to more than `ratio` times the bytes received,
+    /// protecting against decompression bombs.
+    ///
+    /// Only has an effect with the `gzip`, `brotli`, `zstd` or `deflate` features.
+    /// The `maxCompressionRatio` fetch option can only lower this limit.
+    pub fn max_compression_ratio(mut self, ratio: f64) -> Self {
+        self.max_compression_ratio = Some(ratio);
+        self
+    }
+
+    /// Picks the proxy of every request: the system settings (the default), the
+    /// environment variables, a proxy auto-config script, or none.
+    ///
+    /// Once set, the fetch `proxy` option is rejected unless [`Builder::allow_request_proxy`]
+    /// is enabled too.
+    pub fn proxy_mode(mut self, mode: ProxyMode) -> Self {
+        self.proxy_mode = Some(mode);
+        self
+    }
+
+    /// Lets the fetch `proxy` option replace the [`Builder::proxy_mode`].
+    pub fn allow_request_proxy(mut self) -> Self {
+        self.allow_request_proxy = true;
+        self
+    }
+
+    /// Registers a proxy, with its credentials, that the fetch `proxy` option can
+    /// pick by name, e.g. `proxy: "corporate"`. The webview never sees the proxy itself.
+    ///
+    /// Profiles are allowed even without [`Builder::allow_request_proxy`], since the app chose them.
+    /// [`CorsFetch::set_proxy_profile`] changes them at runtime.
+    pub fn proxy_profile(mut self, name: impl Into<String>, proxy: reqwest::Proxy) -> Self {
+        self.proxy_profiles.insert(name.into(), proxy);
+        self
+    }
+
+    /// Customizes the client of every request, after the plugin applied the
+    /// request's own settings, e.g. to set a local address or tune HTTP/2.
+    pub fn client_builder<F>(mut self, f: F) -> Self
+    where
+        F: Fn(reqwest::ClientBuilder) -> reqwest::ClientBuilder + Send + Sync + 'static,
+    {
+        self.client_builder = Some(std::sync::Arc::new(f));
+        self
+    }
+
+    /// Resolves `host` to `addrs` instead of querying DNS, e.g. to point a
+    /// staging hostname at a local server.
+    ///
+    /// A port in the URL takes precedence over the port of the address, and port `0`
+    /// means the default port of the scheme. The fetch `resolve` option can
+    /// override this per request.
+    pub fn resolve(
+        mut self,
+        host: impl Into<String>,
+        addrs: impl IntoIterator<Item = std::net::SocketAddr>,
+    ) -> Self {
+        self.dns
+            .overrides
+            .insert(host.into(), addrs.into_iter().collect());
+        self
+    }
+
+    /// Resolves hostnames with `resolver`, e.g. a DNS-over-HTTPS or DNS-over-TLS client.
+    ///
+    /// Hosts set with [`Builder::resolve`] are not looked up.
+    pub fn dns_resolver(mut self, resolver: impl reqwest::dns::Resolve + 'static) -> Self {
+        self.dns.resolver = Some(std::sync::Arc::new(resolver));
+        self
+    }
+
+    /// Sends requests with `client` unless they set client options of their own:
+    /// `proxy`, `connectTimeout`, `maxRedirections`, `danger`, `maxCompressionRatio`, `resolve`,
+    /// `http1Only`, `http2PriorKnowledge`, `http2KeepAlive` or `unixSocket`.
+    /// Settings like [`Builder::resolve`] and [`Builder::proxy_mode`] are not applied to it.
+    ///
+    /// The plugin's cookie jars are still used, by adding the `Cookie` header to
+    /// the request and storing the cookies of the final response.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Uses the cookie jar called `jar` for requests made from the webview labeled `label`.
+    #[cfg(feature = "cookies")]
+    pub fn webview_cookie_jar(mut self, label: impl Into<String>, jar: impl Into<String>) -> Self {
+        self.webview_cookie_jars.insert(label.into(), jar.into());
+        self
+    }
+
+    /// Lets the webview labeled `label` pick the cookie jar called `jar` with the
+    /// `cookieJar` fetch option.
+    ///
+    /// Without it, a webview can only use the jar it is assigned.
+    #[cfg(feature = "cookies")]
+    pub fn allow_webview_cookie_jar(
+        mut self,
+        label: impl Into<String>,
+        jar: impl Into<String>,
+    ) -> Self {
+        self.allowed_webview_cookie_jars
+            .entry(label.into())
+            .or_default()
+            .insert(jar.into());
+        self
+    }
+
+    /// Lets requests reach a local service over the Unix socket at `path`, with
+    /// the `unixSocket` fetch option or a `http+unix://<percent-encoded path>/` URL.
+    ///
+    /// Paths are compared as given, without resolving symlinks or `..`.
+    #[cfg(unix)]
+    pub fn allow_unix_socket(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.unix_sockets.push(path.into());
+        self
+    }
+
+    /// Gives every webview its own cookie jar, named after the webview label.
+    #[cfg(feature = "cookies")]
+    pub fn cookie_jar_per_webview(mut self) -> Self {
+        self.cookie_jar_per_webview = true;
+        self
+    }
+
+    /// Keeps the cookie jar called `jar` in memory only, e.g. for private sessions.
+    #[cfg(feature = "cookies")]
+    pub fn ephemeral_cookie_jar(mut self, jar: impl Into<String>) -> Self {
+        self.ephemeral_cookie_jars.insert(jar.into());
+        self
+    }
+
+    /// Coalesces cookie jar writes within `debounce` instead of saving on every response.
+    ///
+    /// Defaults to 500ms. Jars are always saved when the app exits.
+    #[cfg(feature = "cookies")]
+    pub fn cookie_save_debounce(mut self, debounce: std::time::Duration) -> Self {
+        self.cookie_save_debounce = Some(debounce);
+        self
+    }
+
+    /// Saves a cookie jar at least once every `max_wait` while its cookies keep changing,
+    /// so a steady stream of responses can't postpone the write forever.
+    ///
+    /// Defaults to 5s.
+    #[cfg(feature = "cookies")]
+    pub fn cookie_save_max_wait(mut self, max_wait: std::time::Duration) -> Self {
+        self.cookie_save_max_wait = Some(max_wait);
+        self
+    }
+
+    /// Mirrors cookies between the plugin cookie jars and the native webview cookie store.
+    #[cfg(feature = "cookies")]
+    pub fn webview_cookie_bridge(mut self, bridge: CookieBridge) -> Self {
+        self.cookie_bridge = Some(bridge);
+        self
+    }
+
+    /// Calls `listener` whenever a cookie is added, updated, expired or removed in any jar.
+    #[cfg(feature = "cookies")]
+    pub fn on_cookie_change<F: Fn(&CookieChange) + Send + Sync + 'static>(
+        mut self,
+        listener: F,
+    ) -> Self {
+        self.cookie_change_listener = Some(std::sync::Arc::new(listener));
+        self
+    }
+
+    /// Emits a [`COOKIE_CHANGE_EVENT`] Tauri event for every cookie change.
+    #[cfg(feature = "cookies")]
+    pub fn emit_cookie_events(mut self) -> Self {
+        self.emit_cookie_events = true;
+        self
+    }
+
+    /// Includes cookie values in change notifications, which are redacted by default.
+    #[cfg(feature = "cookies")]
+    pub fn cookie_event_values(mut self) -> Self {
+        self.cookie_event_values = true;
+        self
+    }
+
+    /// Encrypts persisted cookie jars with ChaCha20-Poly1305 using `key`.
+    ///
+    /// Existing plaintext jars are migrated on load. A jar that can't be
+    /// decrypted, e.g. because the key changed, is replaced by an empty one.
+    #[cfg(feature = "cookies-encryption")]
+    pub fn cookie_jar_key(mut self, key: [u8; 32]) -> Self {
+        self.cookie_jar_key = Some(key);
+        self
+    }
+
+    pub fn build<R: Runtime>(self) -> TauriPlugin<R> {
+        tauri::plugin::Builder::<R>::new("cors-fetch")
+            .setup(|app, _| {
+                let state = Http::new(app, self)?;
+                app.manage(state);
+                Ok(())
+            })
+            .on_event(|app, event| {
+                #[cfg(feature = "cookies")]
+                {
+                    if let tauri::RunEvent::Exit = event {
+                        app.state::<Http>().cookie_jars.save_all();
                     }
                 }
-            }
-        })
-        .invoke_handler(tauri::generate_handler![
-            commands::fetch,
-            commands::fetch_cancel,
-            commands::fetch_send,
-            commands::fetch_read_body,
-            commands::fetch_cancel_body,
-        ])
-        .build()
+                #[cfg(not(feature = "cookies"))]
+                let _ = (app, event);
+            })
+            .invoke_handler(tauri::generate_handler![
+                commands::fetch,
+                commands::fetch_cancel,
+                commands::fetch_send,
+                commands::fetch_read_body,
+                commands::fetch_cancel_body,
+                commands::fetch_ack_body,
+                #[cfg(feature = "cookies")]
+                commands::export_cookies,
+                #[cfg(feature = "cookies")]
+                commands::import_cookies,
+            ])
+            .build()
+    }
+}
+
+pub fn init<R: Runtime>() -> TauriPlugin<R> {
+    Builder::new().build()
 }
@@ -0,0 +1,388 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use http_body::{Body, Frame, SizeHint};
+use serde::Deserialize;
+use tokio::sync::oneshot;
+
+/// The fetch `priority` option, ordering requests waiting in the [`Limiter`] queue.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    High,
+    #[default]
+    Auto,
+    Low,
+}
+
+/// Caps the number of in-flight requests, globally and per host.
+///
+/// Requests over the limit wait in a queue and are let through by priority, then
+/// in arrival order, as permits are released. A queued request that is dropped, e.g. because it was
+/// cancelled, simply leaves the queue.
+#[derive(Debug)]
+pub(crate) struct Limiter {
+    max_total: Option<usize>,
+    max_per_host: Option<usize>,
+    state: Mutex<LimiterState>,
+}
+
+#[derive(Debug, Default)]
+struct LimiterState {
+    in_flight: usize,
+    per_host: HashMap<String, usize>,
+    queue: BTreeMap<QueueKey, Waiter>,
+    next_seq: u64,
+}
+
+/// Orders the queue by priority first, then by arrival.
+type QueueKey = (Priority, u64);
+
+#[derive(Debug)]
+struct Waiter {
+    host: String,
+    tx: oneshot::Sender<()>,
+}
+
+impl LimiterState {
+    fn fits(&self, limiter: &Limiter, host: &str) -> bool {
+        let host_in_flight = self.per_host.get(host).copied().unwrap_or(0);
+        under(self.in_flight, limiter.max_total) && under(host_in_flight, limiter.max_per_host)
+    }
+
+    fn take(&mut self, host: &str) {
+        self.in_flight += 1;
+        *self.per_host.entry(host.to_string()).or_default() += 1;
+    }
+
+    fn release(&mut self, host: &str) {
+        self.in_flight -= 1;
+        if let Some(count) = self.per_host.get_mut(host) {
+            *count -= 1;
+            if *count == 0 {
+                self.per_host.remove(host);
+            }
+        }
+    }
+}
+
+fn under(count: usize, max: Option<usize>) -> bool {
+    match max {
+        Some(max) => count < max,
+        None => true,
+    }
+}
+
+impl Limiter {
+    /// Returns `None` when neither limit is set.
+    pub(crate) fn new(max_total: Option<usize>, max_per_host: Option<usize>) -> Option<Arc<Self>> {
+        (max_total.is_some() || max_per_host.is_some()).then(|| {
+            Arc::new(Self {
+                max_total,
+                max_per_host,
+                state: Default::default(),
+            })
+        })
+    }
+
+    /// Waits until a request to `host` may start.
+    pub(crate) async fn acquire(self: &Arc<Self>, host: &str, priority: Priority) -> Permit {
+        let (key, rx) = {
+            let mut state = self.state.lock().unwrap();
+            // queued requests never fit, otherwise `dispatch` would have started them
+            if state.fits(self, host) {
+                state.take(host);
+                return self.permit(host);
+            }
+
+            let (tx, rx) = oneshot::channel();
+            let key = (priority, state.next_seq);
+            state.next_seq += 1;
+            state.queue.insert(
+                key,
+                Waiter {
+                    host: host.to_string(),
+                    tx,
+                },
+            );
+            (key, rx)
+        };
+
+        let mut queued = Queued {
+            limiter: self.clone(),
+            host: host.to_string(),
+            key,
+            rx,
+            granted: false,
+        };
+        // the sender is only dropped after sending, while we're still queued
+        let _ = (&mut queued.rx).await;
+        queued.granted = true;
+        self.permit(host)
+    }
+
+    fn permit(self: &Arc<Self>, host: &str) -> Permit {
+        Permit {
+            limiter: self.clone(),
+            host: host.to_string(),
+        }
+    }
+
+    /// Releases a slot for `host` and hands the freed capacity to queued requests.
+    fn release(&self, host: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.release(host);
+        self.dispatch(&mut state);
+    }
+
+    fn dispatch(&self, state: &mut LimiterState) {
+        loop {
+            // the first waiter that fits, so a busy host doesn't block the others
+            let Some(key) = state
+                .queue
+                .iter()
+                .find(|(_, waiter)| state.fits(self, &waiter.host))
+                .map(|(key, _)| *key)
+            else {
+                return;
+            };
+
+            let waiter = state.queue.remove(&key).expect("queued waiter");
+            state.take(&waiter.host);
+            if waiter.tx.send(()).is_err() {
+                state.release(&waiter.host);
+            }
+        }
+    }
+}
+
+/// A request waiting in the [`Limiter`] queue, leaving it when dropped.
+struct Queued {
+    limiter: Arc<Limiter>,
+    host: String,
+    key: QueueKey,
+    rx: oneshot::Receiver<()>,
+    granted: bool,
+}
+
+impl Drop for Queued {
+    fn drop(&mut self) {
+        if self.granted {
+            return;
+        }
+
+        let mut state = self.limiter.state.lock().unwrap();
+        if state.queue.remove(&self.key).is_none() && self.rx.try_recv().is_ok() {
+            // the slot was granted but never used
+            state.release(&self.host);
+            self.limiter.dispatch(&mut state);
+        }
+    }
+}
+
+/// A slot in the [`Limiter`], released when dropped.
+#[derive(Debug)]
+pub(crate) struct Permit {
+    limiter: Arc<Limiter>,
+    host: String,
+}
+
+impl Permit {
+    /// Keeps the slot until the body of `res` is read to the end or dropped.
+    ///
+    /// An empty body, like the one of a `HEAD` request or a `204`, releases it right away.
+    pub(crate) fn hold(self, res: reqwest::Response) -> reqwest::Response {
+        if res.content_length() == Some(0) {
+            return res;
+        }
+        crate::body::map_body(res, |inner| PermitBody {
+            inner,
+            permit: Some(self),
+        })
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.limiter.release(&self.host);
+    }
+}
+
+/// A response body holding a [`Permit`] until its last frame.
+struct PermitBody {
+    inner: reqwest::Body,
+    permit: Option<Permit>,
+}
+
+impl Body for PermitBody {
+    type Data = <reqwest::Body as Body>::Data;
+    type Error = reqwest::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let poll = Pin::new(&mut self.inner).poll_frame(cx);
+        if matches!(poll, Poll::Ready(None | Some(Err(_)))) {
+            self.permit = None;
+        }
+        poll
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn in_flight(limiter: &Limiter) -> usize {
+        limiter.state.lock().unwrap().in_flight
+    }
+
+    fn response(body: &'static str) -> reqwest::Response {
+        http::Response::new(reqwest::Body::from(body)).into()
+    }
+
+    fn queued(limiter: &Limiter) -> usize {
+        limiter.state.lock().unwrap().queue.len()
+    }
+
+    /// Lets spawned tasks run until `count` requests are queued.
+    async fn wait_queued(limiter: &Limiter, count: usize) {
+        while queued(limiter) < count {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn held_permit_is_released_at_the_end_of_the_body() {
+        let limiter = Limiter::new(Some(1), None).unwrap();
+        let mut res = limiter
+            .acquire("a", Priority::Auto)
+            .await
+            .hold(response("body"));
+        assert_eq!(in_flight(&limiter), 1);
+
+        assert_eq!(res.chunk().await.unwrap().as_deref(), Some(&b"body"[..]));
+        assert!(res.chunk().await.unwrap().is_none());
+        // the response itself is still alive
+        assert_eq!(in_flight(&limiter), 0);
+    }
+
+    #[tokio::test]
+    async fn empty_body_releases_its_permit_right_away() {
+        let limiter = Limiter::new(Some(1), None).unwrap();
+        let _res = limiter
+            .acquire("a", Priority::Auto)
+            .await
+            .hold(response(""));
+        assert_eq!(in_flight(&limiter), 0);
+    }
+
+    #[tokio::test]
+    async fn queued_requests_start_by_priority_then_arrival() {
+        let limiter = Limiter::new(Some(1), None).unwrap();
+        let permit = limiter.acquire("a", Priority::Auto).await;
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut tasks = Vec::new();
+        for (index, (label, priority)) in [
+            ("low", Priority::Low),
+            ("auto 1", Priority::Auto),
+            ("high", Priority::High),
+            ("auto 2", Priority::Auto),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            tasks.push(tokio::spawn({
+                let (limiter, order) = (limiter.clone(), order.clone());
+                async move {
+                    let _permit = limiter.acquire("a", priority).await;
+                    order.lock().unwrap().push(label);
+                }
+            }));
+            wait_queued(&limiter, index + 1).await;
+        }
+
+        drop(permit);
+        for task in tasks {
+            task.await.unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), ["high", "auto 1", "auto 2", "low"]);
+        assert_eq!(in_flight(&limiter), 0);
+    }
+
+    #[tokio::test]
+    async fn busy_host_does_not_block_the_others() {
+        let limiter = Limiter::new(Some(2), Some(1)).unwrap();
+        let a = limiter.acquire("a", Priority::Auto).await;
+
+        let queued_a = tokio::spawn({
+            let limiter = limiter.clone();
+            async move {
+                limiter.acquire("a", Priority::High).await;
+            }
+        });
+        wait_queued(&limiter, 1).await;
+
+        let b = limiter.acquire("b", Priority::Low).await;
+        assert_eq!(in_flight(&limiter), 2);
+
+        drop((a, b));
+        queued_a.await.unwrap();
+        assert_eq!(in_flight(&limiter), 0);
+    }
+
+    #[tokio::test]
+    async fn cancelled_request_leaves_the_queue() {
+        let limiter = Limiter::new(Some(1), None).unwrap();
+        let permit = limiter.acquire("a", Priority::Auto).await;
+
+        let waiting = tokio::time::timeout(
+            std::time::Duration::from_millis(10),
+            limiter.acquire("a", Priority::High),
+        );
+        assert!(waiting.await.is_err());
+        assert_eq!(queued(&limiter), 0);
+
+        drop(permit);
+        assert_eq!(in_flight(&limiter), 0);
+    }
+
+    #[tokio::test]
+    async fn slot_granted_to_a_cancelled_request_is_released() {
+        let limiter = Limiter::new(Some(1), None).unwrap();
+        let permit = limiter.acquire("a", Priority::Auto).await;
+
+        let task = tokio::spawn({
+            let limiter = limiter.clone();
+            async move {
+                limiter.acquire("a", Priority::Auto).await;
+            }
+        });
+        wait_queued(&limiter, 1).await;
+
+        // hands the slot to the queued request, which is cancelled before it runs again
+        drop(permit);
+        assert_eq!((queued(&limiter), in_flight(&limiter)), (0, 1));
+        task.abort();
+        assert!(task.await.unwrap_err().is_cancelled());
+        assert_eq!(in_flight(&limiter), 0);
+    }
+}
@@ -0,0 +1,96 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use http::{header, HeaderMap, HeaderName, HeaderValue};
+
+use crate::Result;
+
+/// How a single request header is treated before the request is sent.
+#[derive(Debug, Clone, Default)]
+pub enum HeaderRule {
+    /// Send whatever the webview provided.
+    #[default]
+    Preserve,
+    /// Never send the header.
+    Strip,
+    /// Always send this value, whether or not the webview provided one.
+    Force(String),
+    /// Replace the value only when the webview provided one.
+    Rewrite(String),
+    /// Use the target's own origin, mimicking a same-origin request.
+    SameOrigin,
+}
+
+/// Controls the `Origin` and `Referer` headers sent to a host.
+///
+/// The policy is applied in Rust after the webview headers are collected,
+/// so page scripts cannot override it.
+#[derive(Debug, Clone, Default)]
+pub struct OriginPolicy {
+    origin: HeaderRule,
+    referer: HeaderRule,
+}
+
+impl OriginPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets both `Origin` and `Referer` to the target's own origin.
+    pub fn same_origin() -> Self {
+        Self {
+            origin: HeaderRule::SameOrigin,
+            referer: HeaderRule::SameOrigin,
+        }
+    }
+
+    pub fn origin(mut self, rule: HeaderRule) -> Self {
+        self.origin = rule;
+        self
+    }
+
+    pub fn referer(mut self, rule: HeaderRule) -> Self {
+        self.referer = rule;
+        self
+    }
+
+    pub(crate) fn apply(&self, headers: &mut HeaderMap, url: &url::Url) -> Result<()> {
+        let origin = url.origin().ascii_serialization();
+        apply_rule(&self.origin, header::ORIGIN, headers, &origin)?;
+        // a referrer is a URL, so the bare origin gets a trailing slash
+        apply_rule(
+            &self.referer,
+            header::REFERER,
+            headers,
+            &format!("{origin}/"),
+        )?;
+        Ok(())
+    }
+}
+
+fn apply_rule(
+    rule: &HeaderRule,
+    name: HeaderName,
+    headers: &mut HeaderMap,
+    same_origin: &str,
+) -> Result<()> {
+    match rule {
+        HeaderRule::Preserve => {}
+        HeaderRule::Strip => {
+            headers.remove(name);
+        }
+        HeaderRule::Force(value) => {
+            headers.insert(name, HeaderValue::from_str(value)?);
+        }
+        HeaderRule::Rewrite(value) => {
+            if headers.contains_key(&name) {
+                headers.insert(name, HeaderValue::from_str(value)?);
+            }
+        }
+        HeaderRule::SameOrigin => {
+            headers.insert(name, HeaderValue::from_str(same_origin)?);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,277 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, ToSocketAddrs, UdpSocket},
+    path::PathBuf,
+    sync::{mpsc, Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use boa_engine::{
+    js_string, native_function::NativeFunction, Context, JsArgs, JsResult, JsString, JsValue,
+    Source,
+};
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+use crate::Error;
+
+/// How long the proxy picked for a host is reused before the script is evaluated again.
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// How long a request waits for the script, which may still be downloading, before failing.
+const EVAL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The standard PAC helper functions implemented in JavaScript.
+const PAC_UTILS: &str = include_str!("pac_utils.js");
+
+/// Where a proxy auto-config script is loaded from.
+#[derive(Debug, Clone)]
+pub enum PacSource {
+    /// Downloaded without a proxy, e.g. from a WPAD URL.
+    Url(url::Url),
+    File(PathBuf),
+    Script(String),
+}
+
+impl PacSource {
+    async fn load(self) -> Result<String, String> {
+        match self {
+            Self::Url(url) => {
+                let client = reqwest::Client::builder()
+                    .no_proxy()
+                    .build()
+                    .map_err(|e| e.to_string())?;
+                let res = client
+                    .get(url)
+                    .send()
+                    .await
+                    .and_then(|res| res.error_for_status())
+                    .map_err(|e| e.to_string())?;
+                res.text().await.map_err(|e| e.to_string())
+            }
+            Self::File(path) => tokio::fs::read_to_string(path)
+                .await
+                .map_err(|e| e.to_string()),
+            Self::Script(script) => Ok(script),
+        }
+    }
+}
+
+type Job = (url::Url, mpsc::Sender<Option<url::Url>>);
+
+/// Evaluates a PAC script on its own thread, since the JavaScript engine isn't `Send`.
+pub(crate) struct PacResolver {
+    jobs: Mutex<mpsc::Sender<Job>>,
+    cache: Mutex<HashMap<String, (Instant, Option<url::Url>)>>,
+}
+
+impl PacResolver {
+    pub(crate) fn spawn(source: PacSource) -> Arc<Self> {
+        let (jobs, rx) = mpsc::channel::<Job>();
+        std::thread::spawn(move || {
+            // without a script every request goes direct
+            let mut pac = tauri::async_runtime::block_on(source.load())
+                .and_then(|script| Pac::new(&script))
+                .map_err(|_e| {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("failed to load the proxy auto-config script: {_e}");
+                })
+                .ok();
+            for (url, reply) in rx {
+                let proxy = pac.as_mut().and_then(|pac| pac.find_proxy(&url));
+                let _ = reply.send(proxy);
+            }
+        });
+
+        Arc::new(Self {
+            jobs: Mutex::new(jobs),
+            cache: Default::default(),
+        })
+    }
+
+    /// Evaluates the script for `url` on a blocking thread, unless a recent result is cached,
+    /// so [`PacResolver::find_proxy`] finds it without blocking when the request connects.
+    pub(crate) async fn resolve(self: &Arc<Self>, url: &url::Url) -> crate::Result<()> {
+        let Some(url) = script_url(url) else {
+            return Ok(());
+        };
+        let fresh = self
+            .cache
+            .lock()
+            .unwrap()
+            .get(url.as_str())
+            .is_some_and(|(at, _)| at.elapsed() < CACHE_TTL);
+        if fresh {
+            return Ok(());
+        }
+
+        let resolver = self.clone();
+        tauri::async_runtime::spawn_blocking(move || resolver.evaluate(url)).await??;
+        Ok(())
+    }
+
+    /// The proxy for `url`, or `None` to connect directly, called by reqwest as it connects.
+    ///
+    /// The request URL was resolved beforehand, so only redirects to other hosts
+    /// evaluate the script here, moving the other tasks off this worker thread meanwhile.
+    pub(crate) fn find_proxy(&self, url: &url::Url) -> Option<url::Url> {
+        let stripped = script_url(url)?;
+        if let Some((_, proxy)) = self.cache.lock().unwrap().get(stripped.as_str()) {
+            return proxy.clone();
+        }
+
+        let multi_thread = Handle::try_current()
+            .is_ok_and(|handle| handle.runtime_flavor() == RuntimeFlavor::MultiThread);
+        if !multi_thread {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("no proxy auto-config result for {url}, connecting directly");
+            return None;
+        }
+        tokio::task::block_in_place(|| self.evaluate(stripped))
+            .map_err(|_e| {
+                #[cfg(feature = "tracing")]
+                tracing::error!("no proxy auto-config result for {url}, connecting directly: {_e}");
+            })
+            .ok()
+            .flatten()
+    }
+
+    /// Runs the script for a [`script_url`] and caches the result, blocking until it answers.
+    fn evaluate(&self, url: url::Url) -> crate::Result<Option<url::Url>> {
+        let key = url.to_string();
+        let (reply, rx) = mpsc::channel();
+        self.jobs
+            .lock()
+            .unwrap()
+            .send((url, reply))
+            .map_err(|_| Error::PacTimeout)?;
+        let proxy = rx
+            .recv_timeout(EVAL_TIMEOUT)
+            .map_err(|_| Error::PacTimeout)?;
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(key, (Instant::now(), proxy.clone()));
+        Ok(proxy)
+    }
+}
+
+/// The URL passed to `FindProxyForURL`, which results are cached by.
+///
+/// Like browsers do for https, scripts only see the scheme, host and port of a
+/// request, never its credentials, path or query.
+fn script_url(url: &url::Url) -> Option<url::Url> {
+    url.host_str()?;
+    let mut stripped = url.clone();
+    stripped.set_path("/");
+    stripped.set_query(None);
+    stripped.set_fragment(None);
+    let _ = stripped.set_username("");
+    let _ = stripped.set_password(None);
+    Some(stripped)
+}
+
+struct Pac {
+    context: Context,
+}
+
+impl Pac {
+    fn new(script: &str) -> Result<Self, String> {
+        let mut context = Context::default();
+        Self::init(&mut context, script).map_err(|e| e.to_string())?;
+        Ok(Self { context })
+    }
+
+    fn init(context: &mut Context, script: &str) -> JsResult<()> {
+        context.register_global_callable(
+            js_string!("dnsResolve"),
+            1,
+            NativeFunction::from_fn_ptr(dns_resolve),
+        )?;
+        context.register_global_callable(
+            js_string!("myIpAddress"),
+            0,
+            NativeFunction::from_fn_ptr(my_ip_address),
+        )?;
+        context.eval(Source::from_bytes(PAC_UTILS))?;
+        context.eval(Source::from_bytes(script))?;
+        Ok(())
+    }
+
+    fn find_proxy(&mut self, url: &url::Url) -> Option<url::Url> {
+        // JSON strings are valid JavaScript string literals
+        let call = format!(
+            "FindProxyForURL({}, {})",
+            serde_json::to_string(url.as_str()).ok()?,
+            serde_json::to_string(url.host_str()?).ok()?
+        );
+        let result = self
+            .context
+            .eval(Source::from_bytes(&call))
+            .and_then(|value| value.to_string(&mut self.context))
+            .map_err(|_e| {
+                #[cfg(feature = "tracing")]
+                tracing::error!("FindProxyForURL failed for {url}: {_e}");
+            })
+            .ok()?;
+        parse_result(&result.to_std_string_escaped())
+    }
+}
+
+/// Picks the first usable entry of a result like `PROXY a:8080; SOCKS5 b:1080; DIRECT`.
+fn parse_result(result: &str) -> Option<url::Url> {
+    for entry in result.split(';') {
+        let mut parts = entry.split_whitespace();
+        let Some(kind) = parts.next() else {
+            continue;
+        };
+        let scheme = match kind.to_ascii_uppercase().as_str() {
+            "DIRECT" => return None,
+            "PROXY" | "HTTP" => "http",
+            "HTTPS" => "https",
+            "SOCKS" | "SOCKS5" if cfg!(feature = "socks") => "socks5",
+            _ => continue,
+        };
+        if let Some(Ok(proxy)) = parts
+            .next()
+            .map(|host| url::Url::parse(&format!("{scheme}://{host}")))
+        {
+            return Some(proxy);
+        }
+    }
+    None
+}
+
+fn dns_resolve(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let host = args
+        .get_or_undefined(0)
+        .to_string(context)?
+        .to_std_string_escaped();
+    let ip = (host.as_str(), 0)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| {
+            addrs
+                .find(|addr| addr.is_ipv4())
+                .map(|addr| addr.ip().to_string())
+        });
+    Ok(match ip {
+        Some(ip) => JsString::from(ip.as_str()).into(),
+        None => JsValue::null(),
+    })
+}
+
+fn my_ip_address(_: &JsValue, _: &[JsValue], _: &mut Context) -> JsResult<JsValue> {
+    // no packet is sent, connecting only picks the outgoing interface
+    let ip = UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| {
+            socket.connect("8.8.8.8:80")?;
+            socket.local_addr()
+        })
+        .map(|addr| addr.ip())
+        .unwrap_or(IpAddr::from([127, 0, 0, 1]));
+    Ok(JsString::from(ip.to_string().as_str()).into())
+}
@@ -0,0 +1,147 @@
+// The helper functions available to proxy auto-config scripts, see
+// https://developer.mozilla.org/en-US/docs/Web/HTTP/Proxy_servers_and_tunneling/Proxy_Auto-Configuration_PAC_file
+// `dnsResolve` and `myIpAddress` are implemented in Rust.
+
+function isPlainHostName(host) {
+  return host.indexOf(".") === -1;
+}
+
+function dnsDomainIs(host, domain) {
+  return (
+    host.length >= domain.length &&
+    host.substring(host.length - domain.length) === domain
+  );
+}
+
+function localHostOrDomainIs(host, hostdom) {
+  return host === hostdom || hostdom.lastIndexOf(host + ".", 0) === 0;
+}
+
+function isValidIpAddress(ip) {
+  return /^\d{1,3}\.\d{1,3}\.\d{1,3}\.\d{1,3}$/.test(ip);
+}
+
+function convertAddr(ip) {
+  var bytes = ip.split(".");
+  return (
+    ((bytes[0] & 0xff) << 24) |
+    ((bytes[1] & 0xff) << 16) |
+    ((bytes[2] & 0xff) << 8) |
+    (bytes[3] & 0xff)
+  );
+}
+
+function isResolvable(host) {
+  return dnsResolve(host) !== null;
+}
+
+function isInNet(host, pattern, mask) {
+  var ip = isValidIpAddress(host) ? host : dnsResolve(host);
+  if (ip === null || !isValidIpAddress(pattern) || !isValidIpAddress(mask)) {
+    return false;
+  }
+  var m = convertAddr(mask);
+  return (convertAddr(ip) & m) === (convertAddr(pattern) & m);
+}
+
+function dnsDomainLevels(host) {
+  return host.split(".").length - 1;
+}
+
+function shExpMatch(str, shexp) {
+  var re = shexp
+    .replace(/[.+^${}()|[\]\\]/g, "\\$&")
+    .replace(/\*/g, ".*")
+    .replace(/\?/g, ".");
+  return new RegExp("^" + re + "$").test(str);
+}
+
+var WEEKDAYS = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+var MONTHS = [
+  "JAN", "FEB", "MAR", "APR", "MAY", "JUN",
+  "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+// Splits the arguments of the time functions from their optional trailing "GMT"
+function timeArgs(args) {
+  args = Array.prototype.slice.call(args);
+  var gmt = args[args.length - 1] === "GMT";
+  if (gmt) args.pop();
+  return { args: args, date: new Date(), gmt: gmt };
+}
+
+function inRange(start, value, end) {
+  return start <= end
+    ? start <= value && value <= end
+    : value >= start || value <= end;
+}
+
+function weekdayRange() {
+  var t = timeArgs(arguments);
+  var day = t.gmt ? t.date.getUTCDay() : t.date.getDay();
+  var start = WEEKDAYS.indexOf(t.args[0]);
+  var end = t.args.length > 1 ? WEEKDAYS.indexOf(t.args[1]) : start;
+  return start !== -1 && end !== -1 && inRange(start, day, end);
+}
+
+function dateRange() {
+  var t = timeArgs(arguments);
+  var d = t.date;
+  var today = {
+    day: t.gmt ? d.getUTCDate() : d.getDate(),
+    month: t.gmt ? d.getUTCMonth() : d.getMonth(),
+    year: t.gmt ? d.getUTCFullYear() : d.getFullYear(),
+  };
+
+  // each argument is a day of the month, a month name or a four digit year
+  var parts = t.args.map(function (arg) {
+    if (typeof arg === "string") return { month: MONTHS.indexOf(arg) };
+    return arg > 31 ? { year: arg } : { day: arg };
+  });
+  if (parts.length === 1) parts.push(parts[0]);
+  if (parts.length % 2 !== 0) return false;
+
+  var start = {};
+  var end = {};
+  var half = parts.length / 2;
+  for (var i = 0; i < half; i++) {
+    Object.assign(start, parts[i]);
+    Object.assign(end, parts[half + i]);
+  }
+
+  // compares dates as numbers like 20240215, using only the fields of the range
+  var key = function (date) {
+    return (
+      (start.year !== undefined ? date.year : 0) * 10000 +
+      (start.month !== undefined ? date.month : 0) * 100 +
+      (start.day !== undefined ? date.day : 0)
+    );
+  };
+  return inRange(key(start), key(today), key(end));
+}
+
+function timeRange() {
+  var t = timeArgs(arguments);
+  var d = t.date;
+  var now =
+    (t.gmt ? d.getUTCHours() : d.getHours()) * 3600 +
+    (t.gmt ? d.getUTCMinutes() : d.getMinutes()) * 60 +
+    (t.gmt ? d.getUTCSeconds() : d.getSeconds());
+  var a = t.args;
+  switch (a.length) {
+    case 1:
+      return inRange(a[0] * 3600, now, a[0] * 3600 + 3599);
+    case 2:
+      return inRange(a[0] * 3600, now, a[1] * 3600 - 1);
+    case 4:
+      return inRange(a[0] * 3600 + a[1] * 60, now, a[2] * 3600 + a[3] * 60 - 1);
+    case 6:
+      return inRange(
+        a[0] * 3600 + a[1] * 60 + a[2],
+        now,
+        a[3] * 3600 + a[4] * 60 + a[5],
+      );
+    default:
+      return false;
+  }
+}
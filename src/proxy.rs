@@ -0,0 +1,130 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::{collections::HashMap, sync::RwLock};
+
+use reqwest::{NoProxy, Proxy};
+
+use crate::{Error, Result};
+
+#[cfg(feature = "pac")]
+pub use crate::pac::PacSource;
+
+/// Where requests are proxied, unless they set their own `proxy` option.
+#[derive(Debug, Clone, Default)]
+pub enum ProxyMode {
+    /// reqwest's default: the `HTTP(S)_PROXY` environment variables, and the OS
+    /// settings with the `system-proxy` feature.
+    #[default]
+    System,
+    /// Only the `HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY` and `NO_PROXY`
+    /// environment variables, or their lowercase versions.
+    Env,
+    /// A proxy auto-config script, evaluated for every host.
+    #[cfg(feature = "pac")]
+    Pac(PacSource),
+    /// Never use a proxy.
+    Direct,
+}
+
+/// The app-wide proxy configuration, applied to every client built by the plugin.
+#[derive(Clone, Default)]
+pub(crate) enum AppProxy {
+    #[default]
+    System,
+    Env,
+    #[cfg(feature = "pac")]
+    Pac(std::sync::Arc<crate::pac::PacResolver>),
+    Direct,
+}
+
+impl AppProxy {
+    pub(crate) fn new(mode: ProxyMode) -> Self {
+        match mode {
+            ProxyMode::System => Self::System,
+            ProxyMode::Env => Self::Env,
+            #[cfg(feature = "pac")]
+            ProxyMode::Pac(source) => Self::Pac(crate::pac::PacResolver::spawn(source)),
+            ProxyMode::Direct => Self::Direct,
+        }
+    }
+
+    /// The proxy auto-config to resolve before sending a request.
+    #[cfg(feature = "pac")]
+    pub(crate) fn pac(&self) -> Option<&std::sync::Arc<crate::pac::PacResolver>> {
+        match self {
+            Self::Pac(resolver) => Some(resolver),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn apply(&self, builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        match self {
+            Self::System => builder,
+            Self::Env => env_proxies(builder.no_proxy()),
+            #[cfg(feature = "pac")]
+            Self::Pac(resolver) => {
+                let resolver = resolver.clone();
+                builder.proxy(Proxy::custom(move |url| resolver.find_proxy(url)))
+            }
+            Self::Direct => builder.no_proxy(),
+        }
+    }
+}
+
+/// Adds the proxies set in the environment, the scheme-specific ones first.
+fn env_proxies(mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+    type ProxyFn = fn(String) -> reqwest::Result<Proxy>;
+    const VARS: [(&str, ProxyFn); 3] = [
+        ("HTTPS_PROXY", Proxy::https),
+        ("HTTP_PROXY", Proxy::http),
+        ("ALL_PROXY", Proxy::all),
+    ];
+
+    for (name, proxy_fn) in VARS {
+        let Some(value) = std::env::var(name)
+            .or_else(|_| std::env::var(name.to_ascii_lowercase()))
+            .ok()
+            .filter(|value| !value.is_empty())
+        else {
+            continue;
+        };
+        match proxy_fn(value) {
+            Ok(proxy) => builder = builder.proxy(proxy.no_proxy(NoProxy::from_env())),
+            Err(_e) => {
+                #[cfg(feature = "tracing")]
+                tracing::error!("invalid proxy in {name}: {_e}");
+            }
+        }
+    }
+    builder
+}
+
+/// Proxies registered in Rust, which the fetch `proxy` option picks by name
+/// without ever seeing their credentials.
+#[derive(Default)]
+pub(crate) struct ProxyProfiles(RwLock<HashMap<String, Proxy>>);
+
+impl ProxyProfiles {
+    pub(crate) fn new(profiles: HashMap<String, Proxy>) -> Self {
+        Self(RwLock::new(profiles))
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Result<Proxy> {
+        self.0
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::UnknownProxyProfile(name.to_string()))
+    }
+
+    pub(crate) fn set(&self, name: String, proxy: Proxy) {
+        self.0.write().unwrap().insert(name, proxy);
+    }
+
+    pub(crate) fn remove(&self, name: &str) -> bool {
+        self.0.write().unwrap().remove(name).is_some()
+    }
+}
@@ -0,0 +1,209 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{Error, Result};
+
+/// What happens to a request that is over its rate limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RateLimitMode {
+    /// Wait until a token is available.
+    #[default]
+    Wait,
+    /// Fail right away with [`Error::RateLimited`].
+    Fail,
+}
+
+/// A token-bucket rate limit, allowing `requests` per `per` on average.
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    requests: u32,
+    per: Duration,
+    burst: u32,
+    mode: RateLimitMode,
+}
+
+impl RateLimit {
+    /// Allows `requests` per `per`, in bursts of up to `requests`.
+    pub fn new(requests: u32, per: Duration) -> Self {
+        Self {
+            requests,
+            per,
+            burst: requests,
+            mode: RateLimitMode::default(),
+        }
+    }
+
+    /// Allows bursts of up to `burst` requests after an idle period.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst;
+        self
+    }
+
+    pub fn mode(mut self, mode: RateLimitMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// The time it takes to refill a single token.
+    fn interval(&self) -> Duration {
+        self.per / self.requests.max(1)
+    }
+}
+
+/// The tokens left for a [`RateLimit`], shared by every request it applies to.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    limit: RateLimit,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    /// Negative while waiting requests have reserved tokens ahead of time.
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        let tokens = limit.burst as f64;
+        Self {
+            limit,
+            state: Mutex::new(BucketState {
+                tokens,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a token, waiting for one or failing depending on the [`RateLimitMode`].
+    pub(crate) async fn acquire(&self) -> Result<()> {
+        let wait = {
+            let mut state = self.state.lock().unwrap();
+            let interval = self.limit.interval().as_secs_f64();
+            let now = Instant::now();
+            let refilled = now.duration_since(state.updated).as_secs_f64() / interval;
+            state.tokens = (state.tokens + refilled).min(self.limit.burst as f64);
+            state.updated = now;
+
+            if state.tokens >= 1.0 {
+                state.tokens -= 1.0;
+                return Ok(());
+            }
+
+            let wait = Duration::from_secs_f64((1.0 - state.tokens) * interval);
+            if self.limit.mode == RateLimitMode::Fail {
+                return Err(Error::RateLimited(wait));
+            }
+            // reserve the token now, so waiting requests are served in order
+            state.tokens -= 1.0;
+            wait
+        };
+
+        let mut reservation = Reservation {
+            bucket: self,
+            used: false,
+        };
+        tokio::time::sleep(wait).await;
+        reservation.used = true;
+        Ok(())
+    }
+}
+
+/// A token reserved by a waiting request, given back if it is dropped before
+/// its turn, e.g. because it was cancelled.
+struct Reservation<'a> {
+    bucket: &'a TokenBucket,
+    used: bool,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if self.used {
+            return;
+        }
+
+        let mut state = self.bucket.state.lock().unwrap();
+        state.tokens = (state.tokens + 1.0).min(self.bucket.limit.burst as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pretends the bucket was last updated `idle` ago.
+    fn idle(bucket: &TokenBucket, idle: Duration) {
+        bucket.state.lock().unwrap().updated -= idle;
+    }
+
+    #[tokio::test]
+    async fn fails_once_the_burst_is_spent() {
+        let bucket =
+            TokenBucket::new(RateLimit::new(2, Duration::from_secs(1)).mode(RateLimitMode::Fail));
+        bucket.acquire().await.unwrap();
+        bucket.acquire().await.unwrap();
+
+        let Err(Error::RateLimited(wait)) = bucket.acquire().await else {
+            panic!("expected the third request to be rate limited");
+        };
+        assert!(wait > Duration::from_millis(400) && wait <= Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn tokens_refill_over_time() {
+        let bucket =
+            TokenBucket::new(RateLimit::new(1, Duration::from_secs(1)).mode(RateLimitMode::Fail));
+        bucket.acquire().await.unwrap();
+        assert!(bucket.acquire().await.is_err());
+
+        idle(&bucket, Duration::from_secs(1));
+        bucket.acquire().await.unwrap();
+        assert!(bucket.acquire().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn refill_is_capped_at_the_burst() {
+        let limit = RateLimit::new(10, Duration::from_secs(1))
+            .burst(3)
+            .mode(RateLimitMode::Fail);
+        let bucket = TokenBucket::new(limit);
+        idle(&bucket, Duration::from_secs(3600));
+
+        for _ in 0..3 {
+            bucket.acquire().await.unwrap();
+        }
+        assert!(bucket.acquire().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn cancelled_requests_give_their_token_back() {
+        let bucket = TokenBucket::new(RateLimit::new(1, Duration::from_secs(1)));
+        bucket.acquire().await.unwrap();
+
+        for _ in 0..3 {
+            let waiting = tokio::time::timeout(Duration::from_millis(10), bucket.acquire());
+            assert!(waiting.await.is_err());
+        }
+        assert!(bucket.state.lock().unwrap().tokens > -0.5);
+    }
+
+    #[tokio::test]
+    async fn waiting_requests_reserve_their_tokens() {
+        let bucket = TokenBucket::new(RateLimit::new(1, Duration::from_millis(50)));
+        bucket.acquire().await.unwrap();
+
+        // the second waiter is queued behind the first one, a full interval later
+        let start = Instant::now();
+        let (first, second) = tokio::join!(bucket.acquire(), bucket.acquire());
+        first.unwrap();
+        second.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(95));
+    }
+}
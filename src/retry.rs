@@ -0,0 +1,282 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::time::{Duration, SystemTime};
+
+use http::{header, Method, StatusCode};
+use serde::Deserialize;
+
+/// Retries requests that failed because of transient network errors or
+/// retryable status codes, with exponential backoff.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff_base: Duration,
+    backoff_cap: Duration,
+    jitter: bool,
+    status_codes: Vec<StatusCode>,
+    retry_after: bool,
+    non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff_base: Duration::from_millis(200),
+            backoff_cap: Duration::from_secs(10),
+            jitter: true,
+            status_codes: vec![
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            retry_after: true,
+            non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Makes up to `max_attempts` attempts in total, including the first one.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..Default::default()
+        }
+    }
+
+    /// Waits `base * 2^n` before the n-th retry, but never longer than `cap`.
+    pub fn backoff(mut self, base: Duration, cap: Duration) -> Self {
+        self.backoff_base = base;
+        self.backoff_cap = cap;
+        self
+    }
+
+    /// Randomizes each delay between half and all of its value, so clients
+    /// that failed together don't retry together. Enabled by default.
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// The response statuses that are retried, 429, 502, 503 and 504 by default.
+    pub fn status_codes(mut self, status_codes: impl IntoIterator<Item = StatusCode>) -> Self {
+        self.status_codes = status_codes.into_iter().collect();
+        self
+    }
+
+    /// Waits as long as the `Retry-After` response header asks. Enabled by default.
+    ///
+    /// A response asking to wait longer than the backoff cap is returned as is.
+    pub fn retry_after(mut self, retry_after: bool) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Also retries methods that aren't idempotent, like `POST` and `PATCH`.
+    pub fn non_idempotent(mut self, non_idempotent: bool) -> Self {
+        self.non_idempotent = non_idempotent;
+        self
+    }
+
+    pub(crate) fn allows(&self, method: &Method) -> bool {
+        self.max_attempts > 1 && self.resends(method)
+    }
+
+    /// Whether a request with `method` may be sent again after a failed attempt.
+    pub(crate) fn resends(&self, method: &Method) -> bool {
+        self.non_idempotent || method.is_idempotent()
+    }
+
+    /// Returns how long to wait before retrying `attempt`, or `None` to give up.
+    pub(crate) fn delay(
+        &self,
+        attempt: u32,
+        result: &reqwest::Result<reqwest::Response>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+
+        let backoff = self.backoff_delay(attempt);
+        match result {
+            Err(e) if e.is_connect() || e.is_timeout() || e.is_request() => Some(backoff),
+            Err(_) => None,
+            Ok(res) if self.status_codes.contains(&res.status()) => {
+                match self.retry_after.then(|| retry_after(res)).flatten() {
+                    Some(delay) if delay > self.backoff_cap => None,
+                    Some(delay) => Some(delay),
+                    None => Some(backoff),
+                }
+            }
+            Ok(_) => None,
+        }
+    }
+
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let delay = self
+            .backoff_base
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.backoff_cap);
+        if self.jitter {
+            delay / 2 + delay.mul_f64(fastrand::f64() / 2.0)
+        } else {
+            delay
+        }
+    }
+}
+
+/// Parses `Retry-After`, either a number of seconds or an HTTP date.
+fn retry_after(res: &reqwest::Response) -> Option<Duration> {
+    let value = res.headers().get(header::RETRY_AFTER)?.to_str().ok()?;
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(at.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// The `retry` fetch option, overriding the Builder's default policy field by field.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RetryConfig {
+    max_attempts: Option<u32>,
+    /// In milliseconds.
+    backoff_base: Option<u64>,
+    /// In milliseconds.
+    backoff_cap: Option<u64>,
+    jitter: Option<bool>,
+    status_codes: Option<Vec<u16>>,
+    retry_after: Option<bool>,
+    allow_non_idempotent: Option<bool>,
+}
+
+impl RetryConfig {
+    pub(crate) fn resolve(self, default: Option<&RetryPolicy>) -> crate::Result<RetryPolicy> {
+        let mut policy = default.cloned().unwrap_or_default();
+        if let Some(max_attempts) = self.max_attempts {
+            policy.max_attempts = max_attempts;
+        }
+        if let Some(base) = self.backoff_base {
+            policy.backoff_base = Duration::from_millis(base);
+        }
+        if let Some(cap) = self.backoff_cap {
+            policy.backoff_cap = Duration::from_millis(cap);
+        }
+        if let Some(jitter) = self.jitter {
+            policy.jitter = jitter;
+        }
+        if let Some(status_codes) = self.status_codes {
+            policy.status_codes = status_codes
+                .into_iter()
+                .map(StatusCode::from_u16)
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(retry_after) = self.retry_after {
+            policy.retry_after = retry_after;
+        }
+        if let Some(non_idempotent) = self.allow_non_idempotent {
+            policy.non_idempotent = non_idempotent;
+        }
+        Ok(policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(
+        status: StatusCode,
+        retry_after: Option<&str>,
+    ) -> reqwest::Result<reqwest::Response> {
+        let mut res = http::Response::builder().status(status);
+        if let Some(retry_after) = retry_after {
+            res = res.header(header::RETRY_AFTER, retry_after);
+        }
+        Ok(res.body(reqwest::Body::from("")).unwrap().into())
+    }
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy::new(10)
+            .backoff(Duration::from_millis(100), Duration::from_secs(1))
+            .jitter(false)
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = policy();
+        let delays = (1..=6)
+            .map(|attempt| policy.backoff_delay(attempt).as_millis())
+            .collect::<Vec<_>>();
+        assert_eq!(delays, [100, 200, 400, 800, 1000, 1000]);
+        assert_eq!(policy.backoff_delay(u32::MAX), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn jitter_keeps_between_half_and_all_of_the_delay() {
+        let policy = policy().jitter(true);
+        for _ in 0..100 {
+            let delay = policy.backoff_delay(3);
+            assert!(delay >= Duration::from_millis(200) && delay <= Duration::from_millis(400));
+        }
+    }
+
+    #[test]
+    fn only_retryable_statuses_are_retried() {
+        let policy = policy();
+        let unavailable = response(StatusCode::SERVICE_UNAVAILABLE, None);
+        assert_eq!(
+            policy.delay(1, &unavailable),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(policy.delay(10, &unavailable), None);
+        assert_eq!(
+            policy.delay(1, &response(StatusCode::INTERNAL_SERVER_ERROR, None)),
+            None
+        );
+        assert_eq!(policy.delay(1, &response(StatusCode::OK, None)), None);
+    }
+
+    #[test]
+    fn retry_after_delta_seconds() {
+        let policy = policy().backoff(Duration::from_millis(100), Duration::from_secs(5));
+        let res = response(StatusCode::TOO_MANY_REQUESTS, Some(" 2 "));
+        assert_eq!(policy.delay(1, &res), Some(Duration::from_secs(2)));
+
+        // asking to wait longer than the cap gives up
+        let res = response(StatusCode::TOO_MANY_REQUESTS, Some("60"));
+        assert_eq!(policy.delay(1, &res), None);
+
+        // ignored when disabled
+        let res = response(StatusCode::TOO_MANY_REQUESTS, Some("2"));
+        assert_eq!(
+            policy.retry_after(false).delay(1, &res),
+            Some(Duration::from_millis(100))
+        );
+    }
+
+    #[test]
+    fn retry_after_http_date() {
+        let policy = policy().backoff(Duration::from_millis(100), Duration::from_secs(60));
+        let at = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(30));
+        let delay = policy
+            .delay(1, &response(StatusCode::SERVICE_UNAVAILABLE, Some(&at)))
+            .unwrap();
+        // the date only has a precision of a second
+        assert!(delay > Duration::from_secs(28) && delay <= Duration::from_secs(30));
+
+        let past = httpdate::fmt_http_date(SystemTime::now() - Duration::from_secs(30));
+        let res = response(StatusCode::SERVICE_UNAVAILABLE, Some(&past));
+        assert_eq!(policy.delay(1, &res), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn malformed_retry_after_falls_back_to_backoff() {
+        let res = response(StatusCode::SERVICE_UNAVAILABLE, Some("soon"));
+        assert_eq!(policy().delay(2, &res), Some(Duration::from_millis(200)));
+    }
+}
@@ -0,0 +1,29 @@
+// Copyright 2019-2023 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+// SPDX-License-Identifier: MIT
+
+use std::path::PathBuf;
+
+use percent_encoding::percent_decode_str;
+
+use crate::{Error, Result};
+
+/// The scheme of URLs that name their Unix socket, like `http+unix://%2Frun%2Fapp.sock/status`.
+pub(crate) const SCHEME: &str = "http+unix";
+
+/// Splits a [`SCHEME`] URL into the socket path and the `http://localhost` URL
+/// requested over it.
+pub(crate) fn split_url(url: &url::Url) -> Result<(PathBuf, url::Url)> {
+    let socket = url
+        .host_str()
+        .filter(|host| !host.is_empty())
+        .ok_or(Error::UrlParseError(url::ParseError::EmptyHost))?;
+    let socket = percent_decode_str(socket)
+        .decode_utf8()
+        .map_err(|_| Error::UnixSocketNotAllowed(socket.to_string()))?;
+
+    let mut http = url::Url::parse("http://localhost")?;
+    http.set_path(url.path());
+    http.set_query(url.query());
+    Ok((PathBuf::from(socket.as_ref()), http))
+}